@@ -1,10 +1,10 @@
-use rcon::{AsyncStdStream, Connection, Error};
+use rcon::{AsyncStdStream, Connection, Error, ServerProfile};
 
 #[async_std::main]
 async fn main() -> Result<(), Error> {
     let address = "localhost:1234";
     let mut conn = <Connection<AsyncStdStream>>::builder()
-        .enable_factorio_quirks(true)
+        .profile(ServerProfile::Factorio)
         .connect(address, "test")
         .await?;
 
@@ -7,7 +7,7 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
-use rcon::{AsyncStdStream, Connection, Error};
+use rcon::{AsyncStdStream, Connection, Error, ServerProfile};
 
 /*
     This example expects a Minecraft with rcon enabled on port 25575
@@ -18,7 +18,7 @@ use rcon::{AsyncStdStream, Connection, Error};
 async fn main() -> Result<(), Error> {
     let address = "localhost:25575";
     let mut conn = <Connection<AsyncStdStream>>::builder()
-        .enable_minecraft_quirks(true)
+        .profile(ServerProfile::Minecraft)
         .connect(address, "test")
         .await?;
 
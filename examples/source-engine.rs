@@ -1,9 +1,10 @@
-use rcon::{AsyncStdStream, Connection, Error};
+use rcon::{AsyncStdStream, Connection, Error, ServerProfile};
 
 #[async_std::main]
 async fn main() -> Result<(), Error> {
     let address = "localhost:27015";
     let mut conn = <Connection<AsyncStdStream>>::builder()
+        .profile(ServerProfile::Source)
         .connect(address, "test")
         .await?;
 
@@ -0,0 +1,158 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Lets many rcon clients share a single connection to a game server.
+//!
+//! Clients authenticate with the proxy's own passwords. Their commands are
+//! multiplexed over one upstream connection, which applies the quirks of the game
+//! server, like Minecraft's delay between packets and maximum command length.
+//!
+//! A command that cannot be executed upstream, e.g. because it is too long or the
+//! game server is unreachable, disconnects the client that sent it, so that clients
+//! cannot mistake the failure for a reply. The client's address and the reason are
+//! logged to stderr, the command itself only with `--verbose`, as commands may
+//! contain secrets.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::sync::Arc;
+
+use clap::Parser;
+use log::{LevelFilter, Log, Metadata, Record};
+use rcon::server::Server;
+use rcon::{Builder, Client, Connection, ServerProfile};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+
+#[derive(Parser)]
+#[command(name = "rcon-proxy", version, about)]
+struct Args {
+    /// The address to accept rcon clients on.
+    #[arg(long, default_value = "0.0.0.0:25575")]
+    listen: String,
+    /// The address of the game server.
+    #[arg(long)]
+    upstream: String,
+    /// The rcon password of the game server.
+    #[arg(long, env = "RCON_UPSTREAM_PASSWORD", hide_env_values = true)]
+    upstream_password: String,
+    /// A password accepted from clients. May be given several times.
+    #[arg(
+        long = "password",
+        env = "RCON_PROXY_PASSWORDS",
+        value_delimiter = ',',
+        required = true,
+        hide_env_values = true
+    )]
+    passwords: Vec<String>,
+    /// The rcon implementation of the game server: generic, minecraft, source,
+    /// factorio, ark, palworld or squad. Detected automatically by default.
+    #[arg(long)]
+    profile: Option<ServerProfile>,
+    /// Also log the commands that failed upstream. They may contain secrets.
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+/// Writes log messages to stderr.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        eprintln!("{:<5} {}", record.level(), record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+/// The connection to the game server, which is re-established after it broke.
+struct Upstream {
+    builder: Builder<TcpStream>,
+    address: String,
+    password: String,
+    client: Mutex<Option<Client>>,
+}
+
+impl Upstream {
+    async fn client(&self) -> rcon::Result<Client> {
+        let mut client = self.client.lock().await;
+
+        if let Some(client) = client.as_ref().filter(|client| !client.is_closed()) {
+            return Ok(client.clone());
+        }
+
+        let conn = self
+            .builder
+            .clone()
+            .connect(&self.address, &self.password)
+            .await?;
+        log::info!("connected to {} ({:?})", self.address, conn.profile());
+
+        Ok(client.insert(conn.spawn_client()).clone())
+    }
+
+    async fn cmd(&self, cmd: &str) -> rcon::Result<String> {
+        self.client().await?.cmd(cmd).await
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    log::set_logger(&StderrLogger).map_err(|err| err.to_string())?;
+    log::set_max_level(if args.verbose {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Info
+    });
+
+    let upstream = Arc::new(Upstream {
+        builder: match args.profile {
+            Some(profile) => Connection::builder().profile(profile),
+            None => Connection::builder().auto_detect(),
+        },
+        address: args.upstream,
+        password: args.upstream_password,
+        client: Mutex::new(None),
+    });
+
+    // fail early on a wrong address or password
+    upstream.client().await?;
+
+    let passwords: HashSet<Vec<u8>> = args.passwords.into_iter().map(String::into_bytes).collect();
+    let server = Server::new(
+        move |password: &[u8]| passwords.contains(password),
+        move |cmd: String| {
+            let upstream = upstream.clone();
+
+            async move {
+                let result = upstream.cmd(&cmd).await;
+
+                // the server logs the client and the error, commands may contain secrets
+                if let Err(err) = &result {
+                    log::debug!("command {:?} failed: {}", cmd, err);
+                }
+
+                result
+            }
+        },
+    );
+
+    let listener = TcpListener::bind(&args.listen).await?;
+    log::info!("listening on {}", listener.local_addr()?);
+
+    server.serve(listener).await;
+
+    Ok(())
+}
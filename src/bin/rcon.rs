@@ -0,0 +1,476 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! A command line rcon client.
+//!
+//! Commands are taken from the arguments, from a file, or from stdin. When stdin is
+//! a terminal, an interactive prompt with line editing and history is started.
+//!
+//! Servers and groups of servers can be named in a config file, see
+//! [`rcon::config`]. The commands are executed on every server of a group.
+//!
+//! Responses are printed without Minecraft formatting codes and control characters
+//! by default. With `--output json` or `--output ndjson`, every command executed on a
+//! server yields a JSON object with the response, how long it took and the error, if
+//! any. A server that cannot be connected to yields an object without a command
+//! instead. In these modes, failures do not stop the remaining commands or servers,
+//! and the exit status signals whether any of them failed.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use clap::{Parser, ValueEnum};
+use rcon::blocking::Connection;
+use rcon::config::{Config, ConfigError, Password, ServerConfig};
+use rcon::formatting::{strip_control_chars, FormattedText};
+use rcon::{Charset, ServerProfile};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Editor, Helper};
+use serde::Serialize;
+
+const HISTORY_FILE: &str = ".rcon_history";
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Parser)]
+#[command(name = "rcon", version, about)]
+struct Args {
+    /// The name of a server or group of servers in the config file.
+    #[arg(short, long, value_name = "NAME", conflicts_with_all = ["host", "port"])]
+    server: Option<String>,
+    /// The config file. Defaults to `rcon/servers.toml` in the config directory.
+    #[arg(long, value_name = "PATH", env = "RCON_CONFIG")]
+    config: Option<PathBuf>,
+    /// The host of the server.
+    #[arg(short = 'H', long, default_value = "localhost")]
+    host: String,
+    /// The rcon port of the server.
+    #[arg(short = 'P', long, default_value_t = 25575)]
+    port: u16,
+    /// The rcon password of the server. Overrides the password in the config file.
+    #[arg(short, long, env = "RCON_PASSWORD", hide_env_values = true)]
+    password: Option<String>,
+    /// The rcon implementation of the server: generic, minecraft, source, factorio,
+    /// ark, palworld or squad. Detected automatically by default.
+    #[arg(long, conflicts_with_all = ["minecraft", "factorio"])]
+    profile: Option<ServerProfile>,
+    /// Enable the quirks of Minecraft servers.
+    #[arg(long, conflicts_with = "factorio")]
+    minecraft: bool,
+    /// Enable the quirks of Factorio servers.
+    #[arg(long)]
+    factorio: bool,
+    /// The charset of commands and responses: utf-8, utf-8-lossy, latin1 or windows-1252.
+    #[arg(long)]
+    charset: Option<Charset>,
+    /// Give up on a response after this many seconds.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    timeout: Option<Duration>,
+    /// Give up on connecting after this many seconds. Defaults to 10.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    connect_timeout: Option<Duration>,
+    /// How to print responses.
+    #[arg(short, long, value_enum, default_value_t = Output::Plain)]
+    output: Output,
+    /// Read commands from a file, one per line, or from stdin if the path is `-`.
+    #[arg(short, long, value_name = "PATH", conflicts_with = "commands")]
+    file: Option<PathBuf>,
+    /// The commands to execute. Without commands, they are read from stdin.
+    commands: Vec<String>,
+}
+
+/// How responses are printed.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Output {
+    /// The response without Minecraft formatting codes and control characters.
+    Plain,
+    /// The response as sent by the server.
+    Raw,
+    /// A JSON array of results once all commands have been executed.
+    Json,
+    /// A JSON object per result, one per line.
+    Ndjson,
+}
+
+/// The result of executing a command on a server, or of connecting to it if there
+/// is no command, as printed in JSON.
+#[derive(Serialize)]
+struct Record {
+    target: String,
+    command: Option<String>,
+    response: Option<String>,
+    duration_ms: f64,
+    packets: Option<usize>,
+    error: Option<ErrorRecord>,
+}
+
+#[derive(Serialize)]
+struct ErrorRecord {
+    kind: &'static str,
+    message: String,
+}
+
+impl ErrorRecord {
+    fn new(err: &(dyn Error + 'static)) -> Self {
+        let kind = match err.downcast_ref::<rcon::Error>() {
+            None if err.is::<ConfigError>() => "config",
+            None => "other",
+            Some(err) => Self::kind(err),
+        };
+
+        Self {
+            kind,
+            message: err.to_string(),
+        }
+    }
+
+    fn kind(err: &rcon::Error) -> &'static str {
+        match err {
+            rcon::Error::Auth => "auth",
+            rcon::Error::CommandTooLong => "command_too_long",
+            rcon::Error::PacketTooLarge { .. } => "packet_too_large",
+            rcon::Error::MalformedPacket(_) => "malformed_packet",
+            rcon::Error::Decode(_) => "decode",
+            rcon::Error::Encode(_) => "encode",
+            rcon::Error::UnexpectedPacket { .. } => "unexpected_packet",
+            rcon::Error::Timeout => "timeout",
+            rcon::Error::Closed => "closed",
+            rcon::Error::Io(_) => "io",
+        }
+    }
+}
+
+fn parse_seconds(seconds: &str) -> Result<Duration, String> {
+    let seconds = seconds.parse::<f64>().map_err(|err| err.to_string())?;
+
+    Duration::try_from_secs_f64(seconds).map_err(|err| err.to_string())
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(true) => ExitCode::SUCCESS,
+        // the failures are part of the output
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("rcon: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run the commands, returning whether all of them succeeded.
+fn run(args: Args) -> Result<bool, Box<dyn Error>> {
+    let mut targets = Targets::connect(&args)?;
+    let result = run_commands(&mut targets, &args);
+
+    // the results are printed even if a command failed
+    let succeeded = targets.finish()?;
+
+    result.map(|()| succeeded)
+}
+
+fn run_commands(targets: &mut Targets, args: &Args) -> Result<(), Box<dyn Error>> {
+    if !args.commands.is_empty() {
+        for cmd in &args.commands {
+            targets.execute(cmd)?;
+        }
+        return Ok(());
+    }
+
+    match &args.file {
+        Some(path) if path.as_os_str() != "-" => {
+            run_script(targets, BufReader::new(File::open(path)?))
+        }
+        Some(_) => run_script(targets, io::stdin().lock()),
+        None if io::stdin().is_terminal() => run_repl(targets),
+        None => run_script(targets, io::stdin().lock()),
+    }
+}
+
+/// The connections to the servers the commands are executed on.
+struct Targets {
+    conns: Vec<(String, Connection<TcpStream>)>,
+    output: Output,
+    /// The results collected for [`Output::Json`].
+    records: Vec<Record>,
+    /// Whether connecting or a command failed.
+    failed: bool,
+}
+
+impl Targets {
+    fn connect(args: &Args) -> Result<Self, Box<dyn Error>> {
+        let servers = match &args.server {
+            Some(name) => {
+                let config = match &args.config {
+                    Some(path) => Config::load(path)?,
+                    None => Config::load_default()?,
+                };
+                let servers = config.targets(name)?;
+
+                servers
+                    .into_iter()
+                    .map(|(name, server)| (name.to_owned(), server.clone()))
+                    .collect()
+            }
+            None => {
+                let password = args.password.clone().ok_or("no password given")?;
+                let server = ServerConfig {
+                    host: args.host.clone(),
+                    port: args.port,
+                    password: Password::Inline(password),
+                    profile: None,
+                    charset: None,
+                    connect_timeout: None,
+                    auth_timeout: None,
+                    command_timeout: None,
+                };
+
+                vec![(args.host.clone(), server)]
+            }
+        };
+
+        let mut targets = Targets {
+            conns: Vec::new(),
+            output: args.output,
+            records: Vec::new(),
+            failed: false,
+        };
+
+        for (name, server) in servers {
+            let start = Instant::now();
+
+            match connect(args, server) {
+                Ok(conn) => targets.conns.push((name, conn)),
+                // in JSON, the other servers are still usable
+                Err(err) if targets.is_json() => targets.record(Record {
+                    target: name,
+                    command: None,
+                    response: None,
+                    duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+                    packets: None,
+                    error: Some(ErrorRecord::new(&*err)),
+                })?,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(targets)
+    }
+
+    fn is_json(&self) -> bool {
+        matches!(self.output, Output::Json | Output::Ndjson)
+    }
+
+    /// Print a result for [`Output::Ndjson`] or collect it for [`Output::Json`].
+    fn record(&mut self, record: Record) -> io::Result<()> {
+        self.failed |= record.error.is_some();
+
+        if self.output == Output::Ndjson {
+            let mut stdout = io::stdout().lock();
+            serde_json::to_writer(&mut stdout, &record)?;
+            writeln!(stdout)?;
+        } else {
+            self.records.push(record);
+        }
+
+        Ok(())
+    }
+
+    /// Execute a command on every server, printing the name of the server before
+    /// its response if there are several.
+    ///
+    /// In JSON, failures are recorded and only failing to print them is an error.
+    fn execute(&mut self, cmd: &str) -> rcon::Result<()> {
+        let several = self.conns.len() > 1;
+
+        for i in 0..self.conns.len() {
+            let (name, conn) = &mut self.conns[i];
+            let start = Instant::now();
+            let result = conn.cmd(cmd);
+            let duration = start.elapsed();
+
+            let response = match self.output {
+                Output::Plain => strip_control_chars(&FormattedText::parse(&result?).to_plain()),
+                Output::Raw => result?,
+                Output::Json | Output::Ndjson => {
+                    let record = Record {
+                        target: name.clone(),
+                        command: Some(cmd.to_owned()),
+                        response: result.as_ref().ok().cloned(),
+                        duration_ms: duration.as_secs_f64() * 1000.0,
+                        packets: result.as_ref().ok().map(|_| conn.last_response_packets()),
+                        error: result.as_ref().err().map(|err| ErrorRecord::new(err)),
+                    };
+
+                    self.record(record)?;
+                    continue;
+                }
+            };
+
+            if several {
+                println!("[{}]", name);
+            }
+            print!("{}", response);
+            if !response.is_empty() && !response.ends_with('\n') {
+                println!();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Print the results collected for [`Output::Json`], returning whether connecting
+    /// and the commands succeeded.
+    fn finish(self) -> io::Result<bool> {
+        if self.output == Output::Json {
+            let mut stdout = io::stdout().lock();
+            serde_json::to_writer_pretty(&mut stdout, &self.records)?;
+            writeln!(stdout)?;
+        }
+
+        Ok(!self.failed)
+    }
+}
+
+/// Connect to a server, with the arguments overriding its settings.
+fn connect(args: &Args, mut server: ServerConfig) -> Result<Connection<TcpStream>, Box<dyn Error>> {
+    if let Some(password) = &args.password {
+        server.password = Password::Inline(password.clone());
+    }
+    if let Some(profile) = &args.profile {
+        server.profile = Some(profile.clone());
+    } else if args.minecraft {
+        server.profile = Some(ServerProfile::Minecraft);
+    } else if args.factorio {
+        server.profile = Some(ServerProfile::Factorio);
+    }
+    server.charset = args.charset.or(server.charset);
+    server.command_timeout = args.timeout.or(server.command_timeout);
+
+    let connect_timeout = args
+        .connect_timeout
+        .or(server.connect_timeout)
+        .unwrap_or(DEFAULT_CONNECT_TIMEOUT);
+    let builder = server.builder().connect_timeout(connect_timeout);
+
+    Ok(builder.connect_blocking(server.address(), &server.password()?)?)
+}
+
+/// Execute the commands of a script, stopping at the first error that is not
+/// [recorded](Targets::execute).
+///
+/// Empty lines and lines starting with `#` are skipped, and a line ending with `\`
+/// continues on the next line.
+fn run_script<R: BufRead>(targets: &mut Targets, script: R) -> Result<(), Box<dyn Error>> {
+    let mut cmd = String::new();
+
+    for line in script.lines() {
+        let line = line?;
+
+        if let Some(line) = line.strip_suffix('\\') {
+            cmd.push_str(line);
+            cmd.push('\n');
+            continue;
+        }
+
+        cmd.push_str(&line);
+
+        if !cmd.trim().is_empty() && !cmd.starts_with('#') {
+            targets.execute(&cmd)?;
+        }
+
+        cmd.clear();
+    }
+
+    Ok(())
+}
+
+fn run_repl(targets: &mut Targets) -> Result<(), Box<dyn Error>> {
+    let mut editor = Editor::<Multiline, FileHistory>::new()?;
+    let history = home_dir().map(|home| home.join(HISTORY_FILE));
+
+    editor.set_helper(Some(Multiline));
+    if let Some(history) = &history {
+        // there is no history on the first run
+        let _ = editor.load_history(history);
+    }
+
+    let result = loop {
+        let line = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break Ok(()),
+            Err(err) => break Err(err.into()),
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let _ = editor.add_history_entry(line.as_str());
+
+        // only errors that leave the connection usable are survivable
+        match targets.execute(&line.replace("\\\n", "\n")) {
+            Ok(()) => {}
+            Err(
+                err @ (rcon::Error::CommandTooLong
+                | rcon::Error::Encode(_)
+                | rcon::Error::Decode(_)
+                | rcon::Error::Timeout),
+            ) => eprintln!("error: {}", err),
+            Err(err) => break Err(err.into()),
+        }
+    };
+
+    if let Some(history) = &history {
+        editor.save_history(history)?;
+    }
+
+    result
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Continues commands ending with `\` on the next line.
+struct Multiline;
+
+impl Validator for Multiline {
+    fn validate(&self, ctx: &mut ValidationContext<'_>) -> rustyline::Result<ValidationResult> {
+        Ok(if ctx.input().ends_with('\\') {
+            ValidationResult::Incomplete
+        } else {
+            ValidationResult::Valid(None)
+        })
+    }
+}
+
+impl Completer for Multiline {
+    type Candidate = String;
+}
+
+impl Hinter for Multiline {
+    type Hint = String;
+}
+
+impl Highlighter for Multiline {}
+
+impl Helper for Multiline {}
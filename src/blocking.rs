@@ -0,0 +1,370 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! A synchronous rcon client for code that does not run inside an async runtime.
+//!
+//! It shares the protocol logic and the [server profiles](crate::ServerProfile) with the
+//! asynchronous [`Connection`](crate::Connection), and is configured with the same
+//! [`Builder`].
+//!
+//! ```no_run
+//! # fn main() -> rcon::Result<()> {
+//! let mut conn = rcon::blocking::Connection::connect("localhost:25575", "hunter2")?;
+//! println!("{}", conn.cmd("list")?);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::handshake::{CommandSettings, Detection};
+use crate::session::{ClientSession, Event};
+use crate::{
+    Builder, Error, Result, ServerProfile, UnexpectedPacketPolicy, DETECT_PROBE_TIMEOUT,
+    READ_BUFFER_SIZE,
+};
+
+type SetReadTimeout<T> = fn(&T, Option<Duration>) -> io::Result<()>;
+
+/// A synchronous connection to an rcon server.
+///
+/// Timeouts, idle timeout [response terminators](crate::ResponseTerminator) and
+/// [auto-detection](Builder::auto_detect) need socket read timeouts, so they are only
+/// available on connections established with [`Builder::connect_blocking`].
+pub struct Connection<T> {
+    io: T,
+    session: ClientSession,
+    profile: ServerProfile,
+    settings: CommandSettings,
+    unexpected_packets: UnexpectedPacketPolicy,
+    command_timeout: Option<Duration>,
+    set_read_timeout: Option<SetReadTimeout<T>>,
+    /// Whether a write failed partway, possibly leaving part of a packet on the
+    /// transport.
+    broken: bool,
+}
+
+impl Connection<TcpStream> {
+    /// Connect to an rcon server.
+    ///
+    /// By default this uses the [Minecraft profile](ServerProfile::Minecraft).
+    /// If you need to customize this behaviour, use a [`Builder`].
+    pub fn connect<A: ToSocketAddrs>(address: A, password: &str) -> Result<Self> {
+        Self::builder()
+            .profile(ServerProfile::Minecraft)
+            .connect_blocking(address, password)
+    }
+}
+
+impl<T: Read + Write> Connection<T> {
+    /// Create a connection builder.
+    pub fn builder() -> Builder<T> {
+        Builder::new()
+    }
+
+    /// The server profile used by this connection.
+    pub fn profile(&self) -> &ServerProfile {
+        &self.profile
+    }
+
+    /// The number of packets the response to the last command consisted of, including
+    /// packets marking its end.
+    pub fn last_response_packets(&self) -> usize {
+        self.session.last_response_packets()
+    }
+
+    /// Execute a command and return its response, decoded with the [configured
+    /// charset](Builder::charset).
+    pub fn cmd(&mut self, cmd: &str) -> Result<String> {
+        let body = self.cmd_bytes(cmd)?;
+
+        self.settings.charset.decode(body)
+    }
+
+    /// Execute a command and return its response, failing with [`Error::Timeout`] if the
+    /// response takes longer than `timeout`.
+    ///
+    /// This overrides the [command timeout](Builder::command_timeout) of the connection.
+    pub fn cmd_with_timeout(&mut self, cmd: &str, timeout: Duration) -> Result<String> {
+        let body = self.cmd_bytes_with_timeout(cmd, Some(timeout))?;
+
+        self.settings.charset.decode(body)
+    }
+
+    /// Execute a command and return the raw bytes of its response.
+    ///
+    /// The command is still encoded with the [configured charset](Builder::charset).
+    pub fn cmd_bytes(&mut self, cmd: &str) -> Result<Vec<u8>> {
+        self.cmd_bytes_with_timeout(cmd, self.command_timeout)
+    }
+
+    fn cmd_bytes_with_timeout(&mut self, cmd: &str, timeout: Option<Duration>) -> Result<Vec<u8>> {
+        if self.broken {
+            return Err(Error::Closed);
+        }
+
+        let cmd = self.settings.encode(cmd)?;
+
+        let id = self.session.command(cmd);
+
+        // stays set if writing fails, e.g. because of the socket's write timeout
+        self.broken = true;
+        self.flush().map_err(|err| {
+            if is_timeout(&err) {
+                Error::Timeout
+            } else {
+                Error::from(err)
+            }
+        })?;
+        self.broken = false;
+
+        match self.wait_for_response(id, timeout) {
+            // the connection stays usable, a late response is discarded
+            Err(err @ (Error::Timeout | Error::UnexpectedPacket { .. })) => {
+                self.session.cancel(id);
+                Err(err)
+            }
+            response => response,
+        }
+    }
+
+    fn auth(&mut self, password: &str, timeout: Option<Duration>) -> Result<()> {
+        let password = self.settings.encode_password(password)?;
+
+        self.session.auth(password);
+        self.flush()?;
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            match self.next_event(deadline)? {
+                Event::Authenticated => return Ok(()),
+                Event::AuthFailed => return Err(Error::Auth),
+                // there is no command to fail during the handshake
+                event => UnexpectedPacketPolicy::Skip.check(&event)?,
+            }
+        }
+    }
+
+    /// Guess the server implementation after a successful authentication, see
+    /// [`Detection`].
+    fn detect_profile(&mut self) -> Result<ServerProfile> {
+        let mut detection = Detection::start(&mut self.session);
+
+        loop {
+            if let Detection::Done(profile) = detection {
+                return Ok(profile);
+            }
+
+            self.flush()?;
+
+            detection = match self.wait_for_any(&detection.awaited(), Some(DETECT_PROBE_TIMEOUT)) {
+                Ok((id, reply)) => detection.on_reply(&mut self.session, id, &reply),
+                Err(Error::Timeout) => detection.on_timeout(&mut self.session),
+                Err(err) => return Err(err),
+            };
+        }
+    }
+
+    fn wait_for_response(&mut self, id: i32, timeout: Option<Duration>) -> Result<Vec<u8>> {
+        Ok(self.wait_for_any(&[id], timeout)?.1)
+    }
+
+    /// Wait for the first response to one of the commands with the given ids.
+    fn wait_for_any(&mut self, ids: &[i32], timeout: Option<Duration>) -> Result<(i32, Vec<u8>)> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            let event = self.next_event(deadline)?;
+            self.unexpected_packets.check(&event)?;
+
+            if let Event::Response { id, body } = event {
+                if ids.contains(&id) {
+                    return Ok((id, body));
+                }
+            }
+        }
+    }
+
+    /// Write all packets queued in the session to the transport.
+    fn flush(&mut self) -> io::Result<()> {
+        for (delay, packet) in self.settings.transmits(&mut self.session) {
+            if let Some(delay) = delay {
+                thread::sleep(delay);
+            }
+
+            self.io.write_all(&packet)?;
+        }
+
+        self.io.flush()
+    }
+
+    fn next_event(&mut self, deadline: Option<Instant>) -> Result<Event> {
+        let mut buf = [0u8; READ_BUFFER_SIZE];
+
+        loop {
+            if let Some(event) = self.session.poll_event() {
+                return Ok(event);
+            }
+
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+
+            if remaining == Some(Duration::ZERO) {
+                return Err(Error::Timeout);
+            }
+
+            let idle_timeout = self.session.idle_timeout();
+            let timeout = match (remaining, idle_timeout) {
+                (Some(remaining), Some(idle_timeout)) => Some(remaining.min(idle_timeout)),
+                (remaining, idle_timeout) => remaining.or(idle_timeout),
+            };
+
+            if let Some(set_read_timeout) = self.set_read_timeout {
+                // a zero timeout is rejected by the socket
+                set_read_timeout(
+                    &self.io,
+                    timeout.map(|timeout| timeout.max(Duration::from_millis(1))),
+                )?;
+            }
+
+            let read = match self.io.read(&mut buf) {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) if is_timeout(&err) => {
+                    if idle_timeout.is_some()
+                        && !matches!(deadline, Some(deadline) if Instant::now() >= deadline)
+                    {
+                        self.session.handle_idle();
+                    }
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            self.session.receive(&buf[..read])?;
+        }
+    }
+}
+
+/// Socket read timeouts fail with `WouldBlock` on Unix and `TimedOut` on Windows.
+fn is_timeout(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+impl<T: Read + Write> Builder<T> {
+    /// Perform a handshake on an existing synchronous connection to an rcon server.
+    ///
+    /// Timeouts, [response terminators](Self::response_terminator) with an idle timeout and
+    /// [auto-detection](Self::auto_detect) need socket read timeouts. Using them with this
+    /// method fails with an [`Unsupported`](io::ErrorKind::Unsupported) IO error, use
+    /// [`connect_blocking`](Builder::connect_blocking) instead.
+    pub fn handshake_blocking(self, io: T, password: &str) -> Result<Connection<T>> {
+        self.blocking_handshake(io, None, password)
+    }
+
+    fn blocking_handshake(
+        self,
+        io: T,
+        set_read_timeout: Option<SetReadTimeout<T>>,
+        password: &str,
+    ) -> Result<Connection<T>> {
+        let (profile, settings) = self.initial_profile();
+
+        if set_read_timeout.is_none()
+            && (self.auth_timeout.is_some()
+                || self.command_timeout.is_some()
+                || self.auto_detect
+                || settings.response_terminator.idle_timeout().is_some())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "timeouts, idle timeout response terminators and auto-detection need socket \
+                 read timeouts, which are not available on this transport",
+            )
+            .into());
+        }
+
+        let (session, settings) = self.session(settings);
+
+        let mut conn = Connection {
+            io,
+            session,
+            profile,
+            settings,
+            unexpected_packets: self.unexpected_packets,
+            command_timeout: self.command_timeout,
+            set_read_timeout,
+            broken: false,
+        };
+
+        conn.auth(password, self.auth_timeout)?;
+
+        if self.auto_detect {
+            let profile = conn.detect_profile()?;
+
+            conn.settings = CommandSettings::apply(self.settings(&profile), &mut conn.session);
+            conn.profile = profile;
+        }
+
+        Ok(conn)
+    }
+}
+
+impl Builder<TcpStream> {
+    /// Connect to an rcon server without an async runtime.
+    ///
+    /// The [connect timeout](Self::connect_timeout) applies to every address `address`
+    /// resolves to. The [command timeout](Self::command_timeout) is also used as the
+    /// socket's write timeout. A command whose write times out fails with
+    /// [`Error::Timeout`], and later commands with [`Error::Closed`], as part of it may
+    /// have been written.
+    pub fn connect_blocking<A: ToSocketAddrs>(
+        self,
+        address: A,
+        password: &str,
+    ) -> Result<Connection<TcpStream>> {
+        let io = match self.connect_timeout {
+            Some(timeout) => connect_timeout(address, timeout)?,
+            None => TcpStream::connect(address)?,
+        };
+
+        io.set_write_timeout(self.command_timeout)?;
+
+        self.blocking_handshake(io, Some(TcpStream::set_read_timeout), password)
+    }
+}
+
+fn connect_timeout<A: ToSocketAddrs>(address: A, timeout: Duration) -> Result<TcpStream> {
+    let mut last_err = None;
+
+    for address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(io) => return Ok(io),
+            Err(err) => last_err = Some(err),
+        }
+    }
+
+    Err(match last_err {
+        Some(err) if is_timeout(&err) => Error::Timeout,
+        Some(err) => err.into(),
+        None => io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "could not resolve to any address",
+        )
+        .into(),
+    })
+}
@@ -0,0 +1,117 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::{Error, ParseNameError, Result};
+
+/// Characters of Windows-1252 in the range `0x80..=0x9F`.
+///
+/// Bytes that are unassigned in Windows-1252 map to the C1 control character
+/// of the same value, like browsers do.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+/// The text encoding used for commands and responses.
+///
+/// The rcon protocol does not specify an encoding. Most servers use UTF-8,
+/// but some (Palworld, older Source mods, plugins writing Latin-1) send bodies
+/// that are not valid UTF-8.
+///
+/// Charsets can be parsed from their names, `utf-8`, `utf-8-lossy`, `latin1` and
+/// `windows-1252`, or the aliases `iso-8859-1` and `cp1252`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    /// UTF-8, failing with [`Error::Decode`] on invalid responses.
+    #[default]
+    Utf8,
+    /// UTF-8, replacing invalid sequences in responses with `U+FFFD`.
+    Utf8Lossy,
+    /// ISO-8859-1, which maps every byte to the code point of the same value.
+    Latin1,
+    /// Windows-1252, the superset of ISO-8859-1 used by Windows.
+    Windows1252,
+}
+
+impl Display for Charset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Charset::Utf8 | Charset::Utf8Lossy => "UTF-8",
+            Charset::Latin1 => "ISO-8859-1",
+            Charset::Windows1252 => "Windows-1252",
+        })
+    }
+}
+
+impl FromStr for Charset {
+    type Err = ParseNameError;
+
+    fn from_str(name: &str) -> std::result::Result<Self, ParseNameError> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Charset::Utf8),
+            "utf-8-lossy" | "utf8-lossy" => Ok(Charset::Utf8Lossy),
+            "latin1" | "iso-8859-1" => Ok(Charset::Latin1),
+            "windows-1252" | "cp1252" => Ok(Charset::Windows1252),
+            _ => Err(ParseNameError::new("charset", name)),
+        }
+    }
+}
+
+impl Charset {
+    /// Decode a response body.
+    pub fn decode(self, bytes: Vec<u8>) -> Result<String> {
+        match self {
+            Charset::Utf8 => String::from_utf8(bytes).map_err(|_| Error::Decode(self)),
+            Charset::Utf8Lossy => Ok(String::from_utf8(bytes)
+                .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())),
+            Charset::Latin1 => Ok(bytes.iter().map(|&b| char::from(b)).collect()),
+            Charset::Windows1252 => Ok(bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(b - 0x80)],
+                    _ => char::from(b),
+                })
+                .collect()),
+        }
+    }
+
+    /// Encode a command or password.
+    ///
+    /// Fails with [`Error::Encode`] if `text` contains characters that
+    /// cannot be represented in this charset.
+    pub fn encode(self, text: &str) -> Result<Cow<'_, [u8]>> {
+        match self {
+            Charset::Utf8 | Charset::Utf8Lossy => Ok(Cow::Borrowed(text.as_bytes())),
+            Charset::Latin1 => text
+                .chars()
+                .map(|c| u8::try_from(u32::from(c)).map_err(|_| Error::Encode(self)))
+                .collect::<Result<Vec<u8>>>()
+                .map(Cow::Owned),
+            Charset::Windows1252 => text
+                .chars()
+                .map(|c| match u8::try_from(u32::from(c)) {
+                    Ok(b) if !(0x80..=0x9F).contains(&b) => Ok(b),
+                    _ => WINDOWS_1252_HIGH
+                        .iter()
+                        .position(|&high| high == c)
+                        .map(|index| 0x80 + index as u8)
+                        .ok_or(Error::Encode(self)),
+                })
+                .collect::<Result<Vec<u8>>>()
+                .map(Cow::Owned),
+        }
+    }
+}
@@ -0,0 +1,421 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Minecraft JSON text components, for rich messages sent with `tellraw` and `title`.
+//!
+//! A [`Component`] is built from its content, styled with builder methods and
+//! followed by [child components](Component::append), which inherit its style.
+//! [`tellraw`] and [`title`] embed it in a command and check that the command fits
+//! into the [`MINECRAFT_MAX_PAYLOAD_SIZE`] accepted by the server.
+//!
+//! ```
+//! use rcon::chat::{tellraw, ClickEvent, Component, HoverEvent};
+//! use rcon::formatting::Color;
+//!
+//! let message = Component::text("Vote for the ")
+//!     .color(Color::Gray)
+//!     .append(
+//!         Component::text("next map")
+//!             .color(Color::Gold)
+//!             .bold(true)
+//!             .click(ClickEvent::RunCommand("/vote".to_owned()))
+//!             .hover(HoverEvent::ShowText(Box::new(Component::text("Click to vote")))),
+//!     );
+//!
+//! assert_eq!(
+//!     tellraw("@a", &message).unwrap(),
+//!     r#"tellraw @a {"text":"Vote for the ","color":"gray","extra":[{"text":"next map","color":"gold","bold":true,"clickEvent":{"action":"run_command","value":"/vote"},"hoverEvent":{"action":"show_text","contents":{"text":"Click to vote"}}}]}"#
+//! );
+//! ```
+//!
+//! Components are serialized in the JSON format understood by Minecraft 1.16 to
+//! 1.21.4.
+
+use std::fmt::{self, Display, Formatter};
+
+use serde::{Serialize, Serializer};
+
+use crate::formatting::Color;
+use crate::{Error, Result, MINECRAFT_MAX_PAYLOAD_SIZE};
+
+/// A JSON text component.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Component {
+    #[serde(flatten)]
+    content: Content,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_color"
+    )]
+    color: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bold: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    italic: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    underlined: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strikethrough: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    obfuscated: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    insertion: Option<String>,
+    #[serde(rename = "clickEvent", skip_serializing_if = "Option::is_none")]
+    click_event: Option<ClickEvent>,
+    #[serde(rename = "hoverEvent", skip_serializing_if = "Option::is_none")]
+    hover_event: Option<HoverEvent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extra: Vec<Component>,
+}
+
+/// What a component displays.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+enum Content {
+    Text {
+        text: String,
+    },
+    Translate {
+        translate: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        with: Vec<Component>,
+    },
+    Score {
+        score: Score,
+    },
+    Selector {
+        selector: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        separator: Option<Box<Component>>,
+    },
+    Keybind {
+        keybind: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Score {
+    name: String,
+    objective: String,
+}
+
+impl Component {
+    fn new(content: Content) -> Self {
+        Component {
+            content,
+            color: None,
+            bold: None,
+            italic: None,
+            underlined: None,
+            strikethrough: None,
+            obfuscated: None,
+            insertion: None,
+            click_event: None,
+            hover_event: None,
+            extra: Vec::new(),
+        }
+    }
+
+    /// A component displaying `text` as it is.
+    pub fn text<S: Into<String>>(text: S) -> Self {
+        Self::new(Content::Text { text: text.into() })
+    }
+
+    /// A component displaying the translation of `key` in the language of the player,
+    /// e.g. `chat.type.announcement`.
+    ///
+    /// The arguments of the translation are added with [`TranslatedBuilder::with`].
+    pub fn translate<S: Into<String>>(key: S) -> TranslatedBuilder {
+        TranslatedBuilder {
+            translate: key.into(),
+            with: Vec::new(),
+        }
+    }
+
+    /// A component displaying the score of `name`, a player name or selector, in
+    /// `objective`.
+    pub fn score<N: Into<String>, O: Into<String>>(name: N, objective: O) -> Self {
+        Self::new(Content::Score {
+            score: Score {
+                name: name.into(),
+                objective: objective.into(),
+            },
+        })
+    }
+
+    /// A component displaying the names of the entities matched by `selector`, e.g.
+    /// `@a[distance=..10]`.
+    ///
+    /// The separator of the names is set with [`SelectorBuilder::separator`].
+    pub fn selector<S: Into<String>>(selector: S) -> SelectorBuilder {
+        SelectorBuilder {
+            selector: selector.into(),
+            separator: None,
+        }
+    }
+
+    /// A component displaying the key bound to `keybind`, e.g. `key.inventory`.
+    pub fn keybind<S: Into<String>>(keybind: S) -> Self {
+        Self::new(Content::Keybind {
+            keybind: keybind.into(),
+        })
+    }
+
+    /// Set the text color.
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Enable or disable bold text. Disabling overrides the style of the parent.
+    pub fn bold(mut self, value: bool) -> Self {
+        self.bold = Some(value);
+        self
+    }
+
+    /// Enable or disable italic text. Disabling overrides the style of the parent.
+    pub fn italic(mut self, value: bool) -> Self {
+        self.italic = Some(value);
+        self
+    }
+
+    /// Enable or disable underlined text. Disabling overrides the style of the parent.
+    pub fn underlined(mut self, value: bool) -> Self {
+        self.underlined = Some(value);
+        self
+    }
+
+    /// Enable or disable struck through text. Disabling overrides the style of the
+    /// parent.
+    pub fn strikethrough(mut self, value: bool) -> Self {
+        self.strikethrough = Some(value);
+        self
+    }
+
+    /// Enable or disable obfuscated text. Disabling overrides the style of the parent.
+    pub fn obfuscated(mut self, value: bool) -> Self {
+        self.obfuscated = Some(value);
+        self
+    }
+
+    /// Set the text inserted into the chat input when the component is shift-clicked.
+    pub fn insertion<S: Into<String>>(mut self, text: S) -> Self {
+        self.insertion = Some(text.into());
+        self
+    }
+
+    /// Set what happens when the component is clicked.
+    pub fn click(mut self, event: ClickEvent) -> Self {
+        self.click_event = Some(event);
+        self
+    }
+
+    /// Set what is shown when the mouse is over the component.
+    pub fn hover(mut self, event: HoverEvent) -> Self {
+        self.hover_event = Some(event);
+        self
+    }
+
+    /// Add a child component, displayed after this component and its previous
+    /// children with the style of this component unless it overrides it.
+    pub fn append(mut self, child: Component) -> Self {
+        self.extra.push(child);
+        self
+    }
+
+    /// The component as compact JSON.
+    pub fn to_json(&self) -> String {
+        // the components only contain strings, numbers and booleans
+        serde_json::to_string(self).expect("text components are serializable")
+    }
+}
+
+impl Display for Component {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_json())
+    }
+}
+
+impl<S: Into<String>> From<S> for Component {
+    fn from(text: S) -> Self {
+        Component::text(text)
+    }
+}
+
+/// A [translated component](Component::translate) being built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranslatedBuilder {
+    translate: String,
+    with: Vec<Component>,
+}
+
+impl TranslatedBuilder {
+    /// Add an argument to the translation.
+    pub fn with(mut self, arg: Component) -> Self {
+        self.with.push(arg);
+        self
+    }
+
+    /// Create the component, for styling it.
+    pub fn build(self) -> Component {
+        Component::new(Content::Translate {
+            translate: self.translate,
+            with: self.with,
+        })
+    }
+}
+
+impl From<TranslatedBuilder> for Component {
+    fn from(builder: TranslatedBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// A [selector component](Component::selector) being built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorBuilder {
+    selector: String,
+    separator: Option<Box<Component>>,
+}
+
+impl SelectorBuilder {
+    /// Set the component separating the names, which defaults to a gray comma.
+    pub fn separator(mut self, separator: Component) -> Self {
+        self.separator = Some(Box::new(separator));
+        self
+    }
+
+    /// Create the component, for styling it.
+    pub fn build(self) -> Component {
+        Component::new(Content::Selector {
+            selector: self.selector,
+            separator: self.separator,
+        })
+    }
+}
+
+impl From<SelectorBuilder> for Component {
+    fn from(builder: SelectorBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// What happens when a component is clicked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", content = "value", rename_all = "snake_case")]
+pub enum ClickEvent {
+    /// Open a URL, which must use `http` or `https`.
+    OpenUrl(String),
+    /// Run a command as the player who clicked. Commands start with `/`.
+    RunCommand(String),
+    /// Put the text into the chat input of the player who clicked.
+    SuggestCommand(String),
+    /// Turn to a page of the book the component is in.
+    ChangePage(u32),
+    /// Copy the text to the clipboard of the player who clicked.
+    CopyToClipboard(String),
+}
+
+/// What is shown when the mouse is over a component.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", content = "contents", rename_all = "snake_case")]
+pub enum HoverEvent {
+    /// Show a text component.
+    ShowText(Box<Component>),
+    /// Show the tooltip of an item.
+    ShowItem {
+        /// The item id, e.g. `minecraft:diamond_sword`.
+        id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        count: Option<u32>,
+    },
+    /// Show the type, UUID and name of an entity.
+    ShowEntity {
+        /// The entity type, e.g. `minecraft:pig`.
+        #[serde(rename = "type")]
+        kind: String,
+        /// The UUID of the entity in its hyphenated form.
+        id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<Box<Component>>,
+    },
+}
+
+/// Where [`title`] displays a component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitleKind {
+    /// The large text in the middle of the screen.
+    Title,
+    /// The text below the title, shown along with the next title.
+    Subtitle,
+    /// The text above the hotbar.
+    Actionbar,
+}
+
+/// A `tellraw` command sending `message` to the players matched by `targets`, a
+/// player name or selector.
+///
+/// Fails with [`Error::CommandTooLong`] if the command exceeds
+/// [`MINECRAFT_MAX_PAYLOAD_SIZE`].
+pub fn tellraw(targets: &str, message: &Component) -> Result<String> {
+    checked(format!("tellraw {} {}", targets, message))
+}
+
+/// A `title` command displaying `title` to the players matched by `targets`, a player
+/// name or selector.
+///
+/// Fails with [`Error::CommandTooLong`] if the command exceeds
+/// [`MINECRAFT_MAX_PAYLOAD_SIZE`].
+pub fn title(targets: &str, kind: TitleKind, title: &Component) -> Result<String> {
+    let kind = match kind {
+        TitleKind::Title => "title",
+        TitleKind::Subtitle => "subtitle",
+        TitleKind::Actionbar => "actionbar",
+    };
+
+    checked(format!("title {} {} {}", targets, kind, title))
+}
+
+fn checked(cmd: String) -> Result<String> {
+    if cmd.len() > MINECRAFT_MAX_PAYLOAD_SIZE {
+        return Err(Error::CommandTooLong);
+    }
+
+    Ok(cmd)
+}
+
+fn serialize_color<S: Serializer>(
+    color: &Option<Color>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    let name = match color {
+        Some(Color::Black) => "black",
+        Some(Color::DarkBlue) => "dark_blue",
+        Some(Color::DarkGreen) => "dark_green",
+        Some(Color::DarkAqua) => "dark_aqua",
+        Some(Color::DarkRed) => "dark_red",
+        Some(Color::DarkPurple) => "dark_purple",
+        Some(Color::Gold) => "gold",
+        Some(Color::Gray) => "gray",
+        Some(Color::DarkGray) => "dark_gray",
+        Some(Color::Blue) => "blue",
+        Some(Color::Green) => "green",
+        Some(Color::Aqua) => "aqua",
+        Some(Color::Red) => "red",
+        Some(Color::LightPurple) => "light_purple",
+        Some(Color::Yellow) => "yellow",
+        Some(Color::White) => "white",
+        Some(Color::Rgb(r, g, b)) => {
+            return serializer.serialize_str(&format!("#{:02x}{:02x}{:02x}", r, g, b))
+        }
+        None => return serializer.serialize_none(),
+    };
+
+    serializer.serialize_str(name)
+}
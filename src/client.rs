@@ -0,0 +1,270 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::collections::HashMap;
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::handshake::CommandSettings;
+use crate::session::Event;
+use crate::{Connection, Error, Result, SleepFn, UnexpectedPacketPolicy, READ_BUFFER_SIZE};
+
+#[derive(Debug)]
+struct Request {
+    cmd: Vec<u8>,
+    reply: oneshot::Sender<Result<Vec<u8>>>,
+}
+
+/// A cloneable handle for executing commands concurrently over a single connection.
+///
+/// Commands are sent to the [`Driver`] of the connection, which pipelines them and
+/// routes each response back to the waiting caller by packet id. Create one with
+/// [`Connection::into_client`].
+#[derive(Debug, Clone)]
+pub struct Client {
+    requests: mpsc::UnboundedSender<Request>,
+    settings: CommandSettings,
+    command_timeout: Option<Duration>,
+    sleep_fn: SleepFn,
+}
+
+impl Client {
+    /// Execute a command and return its response, decoded with the [configured
+    /// charset](crate::Builder::charset).
+    pub async fn cmd(&self, cmd: &str) -> Result<String> {
+        let body = self.cmd_bytes(cmd).await?;
+
+        self.settings.charset.decode(body)
+    }
+
+    /// Execute a command and return its response, failing with [`Error::Timeout`] if the
+    /// response takes longer than `timeout`.
+    ///
+    /// This overrides the [command timeout](crate::Builder::command_timeout) of the connection.
+    pub async fn cmd_with_timeout(&self, cmd: &str, timeout: Duration) -> Result<String> {
+        let body = self.cmd_bytes_with_timeout(cmd, Some(timeout)).await?;
+
+        self.settings.charset.decode(body)
+    }
+
+    /// Execute a command and return the raw bytes of its response.
+    ///
+    /// Fails with [`Error::Closed`] if the driver has stopped.
+    pub async fn cmd_bytes(&self, cmd: &str) -> Result<Vec<u8>> {
+        self.cmd_bytes_with_timeout(cmd, self.command_timeout).await
+    }
+
+    async fn cmd_bytes_with_timeout(
+        &self,
+        cmd: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<u8>> {
+        let cmd = self.settings.encode(cmd)?;
+
+        let (reply, response) = oneshot::channel();
+
+        self.requests
+            .send(Request {
+                cmd: cmd.into_owned(),
+                reply,
+            })
+            .map_err(|_| Error::Closed)?;
+
+        // the driver discards the response once the receiver is gone
+        self.sleep_fn
+            .clone()
+            .limit(timeout, response)
+            .await
+            .ok_or(Error::Timeout)?
+            .map_err(|_| Error::Closed)?
+    }
+
+    /// Whether the driver has stopped, in which case every command fails.
+    pub fn is_closed(&self) -> bool {
+        self.requests.is_closed()
+    }
+}
+
+enum Step {
+    Request(Option<Request>),
+    Read(usize),
+}
+
+/// The background half of a [`Client`], which owns the transport.
+///
+/// The driver does nothing unless [`run`](Self::run) is polled, usually by spawning
+/// it on the runtime of your choice.
+pub struct Driver<T> {
+    conn: Connection<T>,
+    requests: mpsc::UnboundedReceiver<Request>,
+    requests_closed: bool,
+    waiting: HashMap<i32, oneshot::Sender<Result<Vec<u8>>>>,
+    written: bool,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Driver<T> {
+    /// Drive the connection until every [`Client`] has been dropped and all responses
+    /// have been delivered, or until the connection fails.
+    ///
+    /// Commands still waiting for a response when the connection fails are
+    /// completed with [`Error::Closed`].
+    pub async fn run(mut self) -> Result<()> {
+        let result = self.drive().await;
+
+        // dropping the reply senders fails the waiting commands
+        self.waiting.clear();
+        self.requests.close();
+
+        result
+    }
+
+    async fn drive(&mut self) -> Result<()> {
+        let mut buf = [0u8; READ_BUFFER_SIZE];
+
+        loop {
+            while let Some(event) = self.conn.session.poll_event() {
+                self.handle_event(event);
+            }
+
+            // commands whose caller gave up on them, e.g. after a timeout
+            let session = &mut self.conn.session;
+            self.waiting.retain(|&id, reply| {
+                if reply.is_closed() {
+                    session.cancel(id);
+                }
+                !reply.is_closed()
+            });
+
+            if self.requests_closed && self.waiting.is_empty() {
+                return Ok(());
+            }
+
+            let step = match self.conn.session.idle_timeout() {
+                Some(idle_timeout) => {
+                    let mut sleep_fn = self.conn.sleep_fn.clone();
+
+                    match sleep_fn
+                        .timeout(idle_timeout, self.next_step(&mut buf))
+                        .await
+                    {
+                        Some(step) => step?,
+                        None => {
+                            self.conn.session.handle_idle();
+                            continue;
+                        }
+                    }
+                }
+                None => self.next_step(&mut buf).await?,
+            };
+
+            match step {
+                Step::Request(Some(request)) => {
+                    let id = self.conn.session.command(request.cmd);
+
+                    self.waiting.insert(id, request.reply);
+                    self.flush().await?;
+                }
+                Step::Request(None) => self.requests_closed = true,
+                Step::Read(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Step::Read(read) => self.conn.session.receive(&buf[..read])?,
+            }
+        }
+    }
+
+    /// Route an event of the session to the command it belongs to.
+    ///
+    /// Unexpected packets never stop the driver, as that would fail every other command.
+    fn handle_event(&mut self, event: Event) {
+        let (id, reply) = match event {
+            Event::Response { id, body } => (id, Ok(body)),
+            Event::UnexpectedPacket { id, ptype }
+                if self.conn.unexpected_packets == UnexpectedPacketPolicy::Error
+                    && self.waiting.contains_key(&id) =>
+            {
+                self.conn.session.cancel(id);
+                (id, Err(Error::UnexpectedPacket { id, ptype }))
+            }
+            event => {
+                let _ = UnexpectedPacketPolicy::Skip.check(&event);
+                return;
+            }
+        };
+
+        if let Some(waiting) = self.waiting.remove(&id) {
+            // the caller may have given up on the response
+            let _ = waiting.send(reply);
+        }
+    }
+
+    /// Wait for either a new request or data from the server.
+    async fn next_step(&mut self, buf: &mut [u8]) -> io::Result<Step> {
+        let mut buf = ReadBuf::new(buf);
+
+        poll_fn(|cx| {
+            if !self.requests_closed {
+                if let Poll::Ready(request) = self.requests.poll_recv(cx) {
+                    return Poll::Ready(Ok(Step::Request(request)));
+                }
+            }
+
+            Pin::new(&mut self.conn.io)
+                .poll_read(cx, &mut buf)
+                .map_ok(|()| Step::Read(buf.filled().len()))
+        })
+        .await
+    }
+
+    /// Write all packets queued in the session to the transport.
+    ///
+    /// Unlike [`Connection`], the driver sends commands back to back, so the
+    /// command delay applies to every packet but the very first.
+    async fn flush(&mut self) -> io::Result<()> {
+        while let Some(packet) = self.conn.session.poll_transmit() {
+            if let (Some(delay), true) = (self.conn.settings.command_delay, self.written) {
+                self.conn.sleep_fn.call(delay).await;
+            }
+
+            self.conn.io.write_all(&packet).await?;
+            self.written = true;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T> Connection<T> {
+    /// Split the connection into a cloneable [`Client`] and the [`Driver`] that
+    /// has to be run for the client to make progress.
+    ///
+    /// See [`spawn_client`](Self::spawn_client) for running the driver on Tokio.
+    pub fn into_client(self) -> (Client, Driver<T>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let client = Client {
+            requests: sender,
+            settings: self.settings.clone(),
+            command_timeout: self.command_timeout,
+            sleep_fn: self.sleep_fn.clone(),
+        };
+        let driver = Driver {
+            conn: self,
+            requests: receiver,
+            requests_closed: false,
+            waiting: HashMap::new(),
+            written: false,
+        };
+
+        (client, driver)
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+#[cfg(feature = "codec")]
+use bytes::{Buf, BytesMut};
+
+use crate::packet::{Packet, PacketType};
+use crate::{Error, Result};
+
+/// Default for the maximum value accepted in the length field of a packet.
+pub const DEFAULT_MAX_PACKET_SIZE: usize = 1024 * 1024;
+
+/// Smallest possible value of the length field: id, type and the two terminating nulls.
+pub(crate) const MIN_PACKET_SIZE: usize = 10;
+
+/// An IO-free decoder for rcon packets.
+///
+/// Bytes are fed in as they arrive from the transport, in chunks of any size,
+/// and complete packets are taken out with [`decode`](Self::decode).
+#[derive(Debug)]
+pub struct PacketDecoder {
+    buf: Vec<u8>,
+    max_packet_size: usize,
+}
+
+impl Default for PacketDecoder {
+    fn default() -> Self {
+        Self {
+            buf: Vec::new(),
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+        }
+    }
+}
+
+impl PacketDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum value accepted in the length field of a packet.
+    ///
+    /// Larger packets are rejected with [`Error::PacketTooLarge`] before any of their
+    /// body is buffered.
+    pub fn max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
+
+    /// Append received bytes to the internal buffer.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Number of buffered bytes that do not form a complete packet yet.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Decode the next complete packet from the buffered bytes.
+    ///
+    /// Returns `Ok(None)` if more bytes are needed.
+    pub fn decode(&mut self) -> Result<Option<Packet>> {
+        match parse_packet(&self.buf, self.max_packet_size)? {
+            Some((packet, consumed)) => {
+                self.buf.drain(..consumed);
+                Ok(Some(packet))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// A [`tokio_util::codec`] codec for rcon packets.
+///
+/// Wrapping a transport in a [`Framed`](tokio_util::codec::Framed) with this codec
+/// yields a `Stream` of received [`Packet`]s and a `Sink` accepting packets to send.
+#[cfg(feature = "codec")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "codec")))]
+#[derive(Debug, Clone, Copy)]
+pub struct RconCodec {
+    max_packet_size: usize,
+}
+
+#[cfg(feature = "codec")]
+impl Default for RconCodec {
+    fn default() -> Self {
+        Self {
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+        }
+    }
+}
+
+#[cfg(feature = "codec")]
+impl RconCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum value accepted in the length field of a packet.
+    pub fn max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
+}
+
+#[cfg(feature = "codec")]
+impl tokio_util::codec::Decoder for RconCodec {
+    type Item = Packet;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Packet>> {
+        match parse_packet(src, self.max_packet_size)? {
+            Some((packet, consumed)) => {
+                src.advance(consumed);
+                Ok(Some(packet))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(feature = "codec")]
+impl tokio_util::codec::Encoder<Packet> for RconCodec {
+    type Error = Error;
+
+    fn encode(&mut self, packet: Packet, dst: &mut BytesMut) -> Result<()> {
+        self.encode(&packet, dst)
+    }
+}
+
+#[cfg(feature = "codec")]
+impl tokio_util::codec::Encoder<&Packet> for RconCodec {
+    type Error = Error;
+
+    fn encode(&mut self, packet: &Packet, dst: &mut BytesMut) -> Result<()> {
+        let mut buf = Vec::new();
+        packet.encode(&mut buf);
+        dst.extend_from_slice(&buf);
+        Ok(())
+    }
+}
+
+/// Parse a single packet from the start of `buf`.
+///
+/// Returns the packet and the number of bytes it occupied,
+/// or `None` if `buf` does not contain a complete packet yet.
+fn parse_packet(buf: &[u8], max_packet_size: usize) -> Result<Option<(Packet, usize)>> {
+    if buf.len() < 4 {
+        return Ok(None);
+    }
+
+    let length = check_length(read_i32(&buf[0..4]), max_packet_size)?;
+    let total_length = 4 + length;
+
+    if buf.len() < total_length {
+        return Ok(None);
+    }
+
+    let id = read_i32(&buf[4..8]);
+    let ptype = read_i32(&buf[8..12]);
+
+    check_terminator(&buf[total_length - 2..total_length])?;
+
+    let body = buf[12..total_length - 2].to_vec();
+
+    let packet = Packet::new(id, PacketType::from_i32(ptype, true), body);
+
+    Ok(Some((packet, total_length)))
+}
+
+/// Validate the length field of a packet header.
+pub(crate) fn check_length(length: i32, max_packet_size: usize) -> Result<usize> {
+    if length < MIN_PACKET_SIZE as i32 {
+        return Err(Error::MalformedPacket(
+            "length field is smaller than the minimum packet size",
+        ));
+    }
+
+    let length = length as usize;
+
+    if length > max_packet_size {
+        return Err(Error::PacketTooLarge {
+            size: length,
+            max: max_packet_size,
+        });
+    }
+
+    Ok(length)
+}
+
+/// Validate the two nulls terminating a packet.
+pub(crate) fn check_terminator(terminator: &[u8]) -> Result<()> {
+    if terminator != [0x00, 0x00] {
+        return Err(Error::MalformedPacket(
+            "packet is not terminated by two nulls",
+        ));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn read_i32(bytes: &[u8]) -> i32 {
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(bytes);
+    i32::from_le_bytes(buf)
+}
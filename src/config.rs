@@ -0,0 +1,393 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Named servers and groups of servers in a TOML config file.
+//!
+//! The default config file is `rcon/servers.toml` in the user's config directory,
+//! usually `~/.config/rcon/servers.toml`:
+//!
+//! ```toml
+//! [servers.lobby]
+//! host = "mc.example.com"
+//! password = { env = "LOBBY_RCON_PASSWORD" }
+//! profile = "minecraft"
+//! command_timeout = 5
+//!
+//! [servers.factory]
+//! host = "factorio.example.com"
+//! port = 27015
+//! password = { command = "pass show factorio/rcon" }
+//! profile = "factorio"
+//!
+//! [groups]
+//! all = ["lobby", "factory"]
+//! ```
+//!
+//! The same file is used by the `rcon` command line client.
+//!
+//! ```no_run
+//! # #[cfg(feature = "rt-tokio")]
+//! # async fn doc() -> Result<(), Box<dyn std::error::Error>> {
+//! use rcon::config::Config;
+//! use tokio::net::TcpStream;
+//!
+//! let config = Config::load_default()?;
+//! let server = config.server("lobby")?;
+//! let mut conn = server
+//!     .builder::<TcpStream>()
+//!     .connect(server.address(), &server.password()?)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::de::{self, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+pub use crate::error::ConfigError;
+use crate::{Builder, Charset, ServerProfile};
+
+const DEFAULT_PORT: u16 = 25575;
+
+/// The contents of a config file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The servers by name.
+    #[serde(default)]
+    pub servers: BTreeMap<String, ServerConfig>,
+    /// Groups of servers by name, listing the names of their servers.
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<String>>,
+}
+
+impl Config {
+    /// The path of the default config file, `rcon/servers.toml` in `$XDG_CONFIG_HOME`,
+    /// `~/.config` or `%APPDATA%`.
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+
+        Some(config_dir.join("rcon").join("servers.toml"))
+    }
+
+    /// Load the [default config file](Self::default_path).
+    pub fn load_default() -> Result<Self, ConfigError> {
+        Self::load(Self::default_path().ok_or(ConfigError::NoConfigDir)?)
+    }
+
+    /// Load a config file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let config = fs::read_to_string(path)
+            .map_err(|err| ConfigError::Read(path.display().to_string(), err))?;
+
+        config.parse()
+    }
+
+    /// The server named `name`.
+    pub fn server(&self, name: &str) -> Result<&ServerConfig, ConfigError> {
+        self.servers
+            .get(name)
+            .ok_or_else(|| ConfigError::UnknownServer(name.to_owned()))
+    }
+
+    /// The servers of the group named `name` with their names, or the server named
+    /// `name` if there is no such group.
+    pub fn targets(&self, name: &str) -> Result<Vec<(&str, &ServerConfig)>, ConfigError> {
+        let names = match self.groups.get(name) {
+            Some(names) => names.iter().map(String::as_str).collect(),
+            None => vec![name],
+        };
+
+        names
+            .into_iter()
+            .map(|name| {
+                self.servers
+                    .get_key_value(name)
+                    .map(|(name, server)| (name.as_str(), server))
+                    .ok_or_else(|| ConfigError::UnknownServer(name.to_owned()))
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(config: &str) -> Result<Self, ConfigError> {
+        toml::from_str(config).map_err(ConfigError::Parse)
+    }
+}
+
+/// A server in a config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfig {
+    /// The host name or IP address of the server.
+    pub host: String,
+    /// The rcon port of the server, 25575 by default.
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Where the rcon password comes from.
+    pub password: Password,
+    /// The [profile](ServerProfile) of the server, parsed from its name. It is
+    /// [detected](Builder::auto_detect) after connecting if not set.
+    #[serde(default, deserialize_with = "from_name")]
+    pub profile: Option<ServerProfile>,
+    /// Overrides the [charset](Builder::charset) of the profile, parsed from its name.
+    #[serde(default, deserialize_with = "from_name")]
+    pub charset: Option<Charset>,
+    /// The [connect timeout](Builder::connect_timeout) in seconds.
+    #[serde(default, deserialize_with = "seconds")]
+    pub connect_timeout: Option<Duration>,
+    /// The [auth timeout](Builder::auth_timeout) in seconds.
+    #[serde(default, deserialize_with = "seconds")]
+    pub auth_timeout: Option<Duration>,
+    /// The [command timeout](Builder::command_timeout) in seconds.
+    #[serde(default, deserialize_with = "seconds")]
+    pub command_timeout: Option<Duration>,
+}
+
+impl ServerConfig {
+    /// The address of the server, for passing to `connect`.
+    pub fn address(&self) -> (&str, u16) {
+        (&self.host, self.port)
+    }
+
+    /// Get the rcon password from its source.
+    pub fn password(&self) -> Result<String, ConfigError> {
+        self.password.resolve().map_err(ConfigError::Password)
+    }
+
+    /// A builder configured with the settings of this server.
+    pub fn builder<T>(&self) -> Builder<T> {
+        let mut builder = match &self.profile {
+            Some(profile) => Builder::new().profile(profile.clone()),
+            None => Builder::new().auto_detect(),
+        };
+
+        if let Some(charset) = self.charset {
+            builder = builder.charset(charset);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.auth_timeout {
+            builder = builder.auth_timeout(timeout);
+        }
+        if let Some(timeout) = self.command_timeout {
+            builder = builder.command_timeout(timeout);
+        }
+
+        builder
+    }
+}
+
+/// Where a password comes from.
+///
+/// In a config file, an inline password is a string, the other sources are tables
+/// with a single key, e.g. `password = { env = "RCON_PASSWORD" }`. Tables with
+/// unknown or several keys are rejected.
+#[derive(Clone)]
+pub enum Password {
+    /// The password itself.
+    Inline(String),
+    /// The value of an environment variable.
+    Env {
+        /// The name of the variable.
+        env: String,
+    },
+    /// The contents of a file, without a trailing newline.
+    File {
+        /// The path of the file.
+        file: PathBuf,
+    },
+    /// The output of a shell command, without a trailing newline.
+    Command {
+        /// The command, run by `sh -c` or `cmd /C` on Windows.
+        command: String,
+    },
+}
+
+impl Debug for Password {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Password::Inline(_) => f.write_str("Inline(..)"),
+            Password::Env { env } => f.debug_struct("Env").field("env", env).finish(),
+            Password::File { file } => f.debug_struct("File").field("file", file).finish(),
+            Password::Command { command } => {
+                f.debug_struct("Command").field("command", command).finish()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Password {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(PasswordVisitor)
+    }
+}
+
+/// The keys of the password sources.
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum Source {
+    Env,
+    File,
+    Command,
+}
+
+struct PasswordVisitor;
+
+impl<'de> Visitor<'de> for PasswordVisitor {
+    type Value = Password;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a password or a table with one of the keys `env`, `file` or `command`")
+    }
+
+    fn visit_str<E: de::Error>(self, password: &str) -> Result<Password, E> {
+        Ok(Password::Inline(password.to_owned()))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Password, A::Error> {
+        let source = map
+            .next_key()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let password = match source {
+            Source::Env => Password::Env {
+                env: map.next_value()?,
+            },
+            Source::File => Password::File {
+                file: map.next_value()?,
+            },
+            Source::Command => Password::Command {
+                command: map.next_value()?,
+            },
+        };
+
+        if map.next_key::<IgnoredAny>()?.is_some() {
+            return Err(de::Error::custom(
+                "a password source must have a single key, one of `env`, `file` or `command`",
+            ));
+        }
+
+        Ok(password)
+    }
+}
+
+impl Password {
+    /// Get the password from its source.
+    pub fn resolve(&self) -> io::Result<String> {
+        match self {
+            Password::Inline(password) => Ok(password.clone()),
+            Password::Env { env } => env::var(env).map_err(|err| {
+                io::Error::new(io::ErrorKind::NotFound, format!("${}: {}", env, err))
+            }),
+            Password::File { file } => Ok(trim_newline(fs::read_to_string(file)?)),
+            Password::Command { command } => {
+                let output = shell(command).output()?;
+
+                if !output.status.success() {
+                    return Err(io::Error::other(format!(
+                        "`{}` failed with {}",
+                        command, output.status
+                    )));
+                }
+
+                String::from_utf8(output.stdout)
+                    .map(trim_newline)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            }
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+fn trim_newline(mut text: String) -> String {
+    let len = text.trim_end_matches(['\r', '\n']).len();
+    text.truncate(len);
+    text
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+fn from_name<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let name = String::deserialize(deserializer)?;
+
+    name.parse().map(Some).map_err(de::Error::custom)
+}
+
+fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    let seconds = f64::deserialize(deserializer)?;
+
+    Duration::try_from_secs_f64(seconds)
+        .map(Some)
+        .map_err(de::Error::custom)
+}
+
+impl<T> Builder<T> {
+    /// Create a builder configured with the settings of the server named `name` in
+    /// the [default config file](Config::default_path).
+    ///
+    /// The server is returned alongside the builder for its address and password:
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "rt-tokio")]
+    /// # async fn doc() -> Result<(), Box<dyn std::error::Error>> {
+    /// use rcon::Builder;
+    /// use tokio::net::TcpStream;
+    ///
+    /// let (builder, server) = Builder::<TcpStream>::from_config("lobby")?;
+    /// let mut conn = builder
+    ///     .connect(server.address(), &server.password()?)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "config")))]
+    pub fn from_config(name: &str) -> Result<(Self, ServerConfig), ConfigError> {
+        let server = Config::load_default()?.server(name)?.clone();
+
+        Ok((server.builder(), server))
+    }
+}
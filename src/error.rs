@@ -0,0 +1,81 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! The error types of the crate.
+// err-derive generates its impl blocks inside of anonymous constants
+#![allow(non_local_definitions)]
+
+use std::io;
+
+use err_derive::Error;
+
+use crate::{Charset, PacketType};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(display = "authentication failed")]
+    Auth,
+    #[error(display = "command exceeds the maximum length")]
+    CommandTooLong,
+    #[error(
+        display = "packet of {} bytes exceeds the maximum of {} bytes",
+        size,
+        max
+    )]
+    PacketTooLarge { size: usize, max: usize },
+    #[error(display = "malformed packet: {}", _0)]
+    MalformedPacket(&'static str),
+    #[error(display = "response is not valid {}", _0)]
+    Decode(Charset),
+    #[error(display = "command cannot be encoded as {}", _0)]
+    Encode(Charset),
+    #[error(display = "unexpected {:?} packet with id {}", ptype, id)]
+    UnexpectedPacket { id: i32, ptype: PacketType },
+    #[error(display = "operation timed out")]
+    Timeout,
+    #[error(display = "connection closed")]
+    Closed,
+    #[error(display = "{}", _0)]
+    Io(#[error(source)] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The error returned when parsing a [`ServerProfile`](crate::ServerProfile) or [`Charset`] from an unknown name.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error(display = "unknown {} `{}`", kind, name)]
+pub struct ParseNameError {
+    kind: &'static str,
+    name: String,
+}
+
+impl ParseNameError {
+    pub(crate) fn new(kind: &'static str, name: &str) -> Self {
+        Self {
+            kind,
+            name: name.to_owned(),
+        }
+    }
+}
+
+/// An error loading a config file or resolving a server from it.
+#[cfg(feature = "config")]
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error(display = "cannot read {}: {}", _0, _1)]
+    Read(String, #[error(source)] io::Error),
+    #[error(display = "invalid config: {}", _0)]
+    Parse(#[error(source)] toml::de::Error),
+    #[error(display = "no config directory found")]
+    NoConfigDir,
+    #[error(display = "no server or group named `{}`", _0)]
+    UnknownServer(String),
+    #[error(display = "cannot get the password: {}", _0)]
+    Password(#[error(source)] io::Error),
+}
@@ -0,0 +1,383 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Minecraft formatting codes, like `§a` for green or `§l` for bold text.
+//!
+//! Responses of Minecraft servers and their plugins are often styled with
+//! section sign codes. [`FormattedText::parse`] splits such a response into
+//! [spans](Span) of uniformly styled text, which can be rendered as plain text,
+//! as ANSI escape sequences for terminals or as HTML.
+//!
+//! ```
+//! use rcon::formatting::{Color, FormattedText};
+//!
+//! let text = FormattedText::parse("§aWelcome §lback§r!");
+//!
+//! assert_eq!(text.spans()[1].style.color, Some(Color::Green));
+//! assert!(text.spans()[1].style.bold);
+//! assert_eq!(text.to_plain(), "Welcome back!");
+//! assert_eq!(text.to_ansi(), "\x1b[92mWelcome \x1b[0m\x1b[92;1mback\x1b[0m!");
+//! ```
+//!
+//! Hex colors are written as `§x` followed by the six hex digits of the color,
+//! each preceded by a section sign, e.g. `§x§f§f§a§a§0§0`. Unknown codes are
+//! removed, like the game does.
+
+use std::fmt::Write;
+
+/// The section sign starting a formatting code.
+pub const SECTION_SIGN: char = '§';
+
+/// A text color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    Black,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White,
+    /// A hex color.
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// The color selected by a formatting code, `0` to `9` and `a` to `f`.
+    pub fn from_code(code: char) -> Option<Self> {
+        let color = match code.to_ascii_lowercase() {
+            '0' => Color::Black,
+            '1' => Color::DarkBlue,
+            '2' => Color::DarkGreen,
+            '3' => Color::DarkAqua,
+            '4' => Color::DarkRed,
+            '5' => Color::DarkPurple,
+            '6' => Color::Gold,
+            '7' => Color::Gray,
+            '8' => Color::DarkGray,
+            '9' => Color::Blue,
+            'a' => Color::Green,
+            'b' => Color::Aqua,
+            'c' => Color::Red,
+            'd' => Color::LightPurple,
+            'e' => Color::Yellow,
+            'f' => Color::White,
+            _ => return None,
+        };
+
+        Some(color)
+    }
+
+    /// The red, green and blue components of the color as displayed by the game.
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Black => (0x00, 0x00, 0x00),
+            Color::DarkBlue => (0x00, 0x00, 0xaa),
+            Color::DarkGreen => (0x00, 0xaa, 0x00),
+            Color::DarkAqua => (0x00, 0xaa, 0xaa),
+            Color::DarkRed => (0xaa, 0x00, 0x00),
+            Color::DarkPurple => (0xaa, 0x00, 0xaa),
+            Color::Gold => (0xff, 0xaa, 0x00),
+            Color::Gray => (0xaa, 0xaa, 0xaa),
+            Color::DarkGray => (0x55, 0x55, 0x55),
+            Color::Blue => (0x55, 0x55, 0xff),
+            Color::Green => (0x55, 0xff, 0x55),
+            Color::Aqua => (0x55, 0xff, 0xff),
+            Color::Red => (0xff, 0x55, 0x55),
+            Color::LightPurple => (0xff, 0x55, 0xff),
+            Color::Yellow => (0xff, 0xff, 0x55),
+            Color::White => (0xff, 0xff, 0xff),
+            Color::Rgb(r, g, b) => (r, g, b),
+        }
+    }
+
+    /// The SGR parameters selecting the color as foreground color of a terminal.
+    ///
+    /// The named colors map to the 16 standard terminal colors, so they follow the
+    /// terminal's color scheme.
+    fn ansi(self) -> String {
+        let code = match self {
+            Color::Black => 30,
+            Color::DarkRed => 31,
+            Color::DarkGreen => 32,
+            Color::Gold => 33,
+            Color::DarkBlue => 34,
+            Color::DarkPurple => 35,
+            Color::DarkAqua => 36,
+            Color::Gray => 37,
+            Color::DarkGray => 90,
+            Color::Red => 91,
+            Color::Green => 92,
+            Color::Yellow => 93,
+            Color::Blue => 94,
+            Color::LightPurple => 95,
+            Color::Aqua => 96,
+            Color::White => 97,
+            Color::Rgb(r, g, b) => return format!("38;2;{};{};{}", r, g, b),
+        };
+
+        code.to_string()
+    }
+}
+
+/// The style of a span of text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Style {
+    /// The text color, or the default color of where the text is displayed.
+    pub color: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underlined: bool,
+    pub strikethrough: bool,
+    /// Text that the game displays as randomly changing characters.
+    pub obfuscated: bool,
+}
+
+impl Style {
+    /// Whether this is the default style, without any formatting.
+    pub fn is_plain(&self) -> bool {
+        *self == Style::default()
+    }
+
+    /// A style with `color` and without any other formatting, as selected by a color code.
+    fn colored(color: Color) -> Self {
+        Style {
+            color: Some(color),
+            ..Style::default()
+        }
+    }
+}
+
+/// A span of uniformly styled text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+/// Text split into styled spans.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FormattedText {
+    spans: Vec<Span>,
+}
+
+impl FormattedText {
+    /// Parse the formatting codes in `text`.
+    ///
+    /// A color code resets the formatting, as it does in the game, and `§r` resets
+    /// both color and formatting. Consecutive spans have different styles, and no
+    /// span is empty.
+    pub fn parse(text: &str) -> Self {
+        let mut parsed = FormattedText::default();
+        let mut style = Style::default();
+        let mut rest = text;
+
+        loop {
+            // take the text up to the next code
+            let end = rest.find(SECTION_SIGN).unwrap_or(rest.len());
+            parsed.push(&rest[..end], style);
+            let mut chars = rest[end..].chars();
+
+            if chars.next().is_none() {
+                break;
+            }
+
+            match chars.next().map(|code| code.to_ascii_lowercase()) {
+                Some('x') => {
+                    // an unparseable hex color is an unknown code
+                    if let Some(color) = parse_hex_color(&mut chars.clone()) {
+                        style = Style::colored(color);
+                        chars.nth(11);
+                    }
+                }
+                Some('k') => style.obfuscated = true,
+                Some('l') => style.bold = true,
+                Some('m') => style.strikethrough = true,
+                Some('n') => style.underlined = true,
+                Some('o') => style.italic = true,
+                Some('r') => style = Style::default(),
+                Some(code) => {
+                    if let Some(color) = Color::from_code(code) {
+                        style = Style::colored(color);
+                    }
+                }
+                None => {}
+            }
+
+            rest = chars.as_str();
+        }
+
+        parsed
+    }
+
+    /// The styled spans of the text.
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// Take the styled spans of the text.
+    pub fn into_spans(self) -> Vec<Span> {
+        self.spans
+    }
+
+    /// The text without any formatting.
+    pub fn to_plain(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    /// The text with ANSI escape sequences for styling it in a terminal.
+    ///
+    /// Named colors use the 16 standard colors of the terminal, hex colors need a
+    /// terminal supporting 24-bit colors. Obfuscated text blinks. The formatting is
+    /// reset after every styled span. [Control characters](strip_control_chars) are
+    /// removed, so the text cannot send escape sequences of its own.
+    pub fn to_ansi(&self) -> String {
+        let mut ansi = String::new();
+
+        for span in &self.spans {
+            let text = strip_control_chars(&span.text);
+
+            if span.style.is_plain() {
+                ansi.push_str(&text);
+                continue;
+            }
+
+            let style = &span.style;
+            let params: Vec<String> = style
+                .color
+                .map(Color::ansi)
+                .into_iter()
+                .chain(style.bold.then(|| "1".to_owned()))
+                .chain(style.italic.then(|| "3".to_owned()))
+                .chain(style.underlined.then(|| "4".to_owned()))
+                .chain(style.obfuscated.then(|| "5".to_owned()))
+                .chain(style.strikethrough.then(|| "9".to_owned()))
+                .collect();
+
+            write!(ansi, "\x1b[{}m{}\x1b[0m", params.join(";"), text).unwrap();
+        }
+
+        ansi
+    }
+
+    /// The text as HTML, with styled spans in `<span>` elements with inline styles.
+    ///
+    /// Obfuscated spans have the `obfuscated` class for styling or animating them.
+    /// Line breaks are kept as they are, so the HTML should be displayed with
+    /// `white-space: pre-wrap`.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+
+        for span in &self.spans {
+            if span.style.is_plain() {
+                escape_html(&mut html, &span.text);
+                continue;
+            }
+
+            let style = &span.style;
+            let mut css = Vec::new();
+            let mut decorations = Vec::new();
+
+            if let Some(color) = style.color {
+                let (r, g, b) = color.rgb();
+                css.push(format!("color:#{:02x}{:02x}{:02x}", r, g, b));
+            }
+            if style.bold {
+                css.push("font-weight:bold".to_owned());
+            }
+            if style.italic {
+                css.push("font-style:italic".to_owned());
+            }
+            if style.underlined {
+                decorations.push("underline");
+            }
+            if style.strikethrough {
+                decorations.push("line-through");
+            }
+            if !decorations.is_empty() {
+                css.push(format!("text-decoration:{}", decorations.join(" ")));
+            }
+
+            html.push_str("<span");
+            if style.obfuscated {
+                html.push_str(" class=\"obfuscated\"");
+            }
+            if !css.is_empty() {
+                write!(html, " style=\"{}\"", css.join(";")).unwrap();
+            }
+            html.push('>');
+            escape_html(&mut html, &span.text);
+            html.push_str("</span>");
+        }
+
+        html
+    }
+
+    /// Append text with `style`, merging it into the last span if it has the same style.
+    fn push(&mut self, text: &str, style: Style) {
+        if text.is_empty() {
+            return;
+        }
+
+        match self.spans.last_mut() {
+            Some(last) if last.style == style => last.text.push_str(text),
+            _ => self.spans.push(Span {
+                text: text.to_owned(),
+                style,
+            }),
+        }
+    }
+}
+
+/// Remove control characters other than line breaks and tabs from `text`.
+///
+/// Text from a server should be passed through this before printing it to a
+/// terminal, where control characters could move the cursor, clear the screen or
+/// start escape sequences.
+pub fn strip_control_chars(text: &str) -> String {
+    text.chars()
+        .filter(|&c| !c.is_control() || c == '\n' || c == '\t')
+        .collect()
+}
+
+/// Parse the `§R§R§G§G§B§B` following `§x`.
+fn parse_hex_color(chars: &mut std::str::Chars<'_>) -> Option<Color> {
+    let mut rgb = 0u32;
+
+    for _ in 0..6 {
+        if chars.next()? != SECTION_SIGN {
+            return None;
+        }
+        rgb = rgb << 4 | chars.next()?.to_digit(16)?;
+    }
+
+    Some(Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
+
+fn escape_html(html: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+}
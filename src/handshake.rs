@@ -0,0 +1,192 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! The sans-IO steps of the handshake shared by the async and the blocking connections.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::packet::check_body_len;
+use crate::profile::FACTORIO_VERSION_COMMAND;
+use crate::{
+    Builder, Charset, ClientSession, Error, ProfileSettings, Result, ServerProfile, SinglePacket,
+};
+
+/// The settings of a profile that apply to individual commands.
+#[derive(Debug, Clone)]
+pub(crate) struct CommandSettings {
+    pub(crate) max_command_len: Option<usize>,
+    pub(crate) command_delay: Option<Duration>,
+    pub(crate) charset: Charset,
+}
+
+impl CommandSettings {
+    /// Hand the response terminator of `settings` to `session` and keep the rest.
+    pub(crate) fn apply(settings: ProfileSettings, session: &mut ClientSession) -> Self {
+        session.set_response_terminator(settings.response_terminator);
+
+        Self {
+            max_command_len: settings.max_command_len,
+            command_delay: settings.command_delay,
+            charset: settings.charset,
+        }
+    }
+
+    /// Encode a command, failing with [`Error::CommandTooLong`] if the profile does
+    /// not allow commands of its length, or with [`Error::PacketTooLarge`] if it does
+    /// not fit into a packet.
+    pub(crate) fn encode<'a>(&self, cmd: &'a str) -> Result<Cow<'a, [u8]>> {
+        let cmd = self.charset.encode(cmd)?;
+
+        if matches!(self.max_command_len, Some(max) if cmd.len() > max) {
+            return Err(Error::CommandTooLong);
+        }
+        check_body_len(cmd.len())?;
+
+        Ok(cmd)
+    }
+
+    /// Encode a password, failing with [`Error::PacketTooLarge`] if it does not fit
+    /// into a packet.
+    pub(crate) fn encode_password<'a>(&self, password: &'a str) -> Result<Cow<'a, [u8]>> {
+        let password = self.charset.encode(password)?;
+
+        check_body_len(password.len())?;
+
+        Ok(password)
+    }
+
+    /// Take the packets queued in `session`, each with the delay to wait before
+    /// writing it.
+    pub(crate) fn transmits<'a>(
+        &self,
+        session: &'a mut ClientSession,
+    ) -> impl Iterator<Item = (Option<Duration>, Vec<u8>)> + 'a {
+        let command_delay = self.command_delay;
+        let mut first = true;
+
+        std::iter::from_fn(move || {
+            let packet = session.poll_transmit()?;
+            // e.g. Minecraft needs a short break between consecutive packets
+            let delay = command_delay.filter(|_| !first);
+
+            first = false;
+            Some((delay, packet))
+        })
+    }
+}
+
+impl<T> Builder<T> {
+    /// The profile used during the handshake and its settings.
+    ///
+    /// When auto-detecting, the profile is only known after authenticating, until then
+    /// plain rcon is used.
+    pub(crate) fn initial_profile(&self) -> (ServerProfile, ProfileSettings) {
+        let profile = if self.auto_detect {
+            ServerProfile::Generic
+        } else {
+            self.profile.clone()
+        };
+        let settings = self.settings(&profile);
+
+        (profile, settings)
+    }
+
+    /// Create the session of a new connection using `settings`.
+    pub(crate) fn session(&self, settings: ProfileSettings) -> (ClientSession, CommandSettings) {
+        let mut session = ClientSession::new().max_packet_size(self.max_packet_size);
+        let settings = CommandSettings::apply(settings, &mut session);
+
+        (session, settings)
+    }
+}
+
+/// Detecting the server implementation after a successful authentication.
+///
+/// Source dedicated servers give themselves away by sending a packet before the
+/// auth response. Other servers are probed with an empty command: Minecraft and ARK
+/// answer it with a characteristic message, while Factorio does not answer at all.
+/// Servers not answering the probe within [`DETECT_PROBE_TIMEOUT`](crate::DETECT_PROBE_TIMEOUT)
+/// are asked for their version, which only Factorio answers with a bare version number,
+/// while a late reply to the probe is still accepted. Servers answering neither are
+/// generic.
+pub(crate) enum Detection {
+    /// Waiting for the reply to the empty command.
+    Probing {
+        probe: i32,
+    },
+    /// Waiting for the reply to the version command, or a late one to the empty command.
+    Confirming {
+        probe: i32,
+        version: i32,
+    },
+    Done(ServerProfile),
+}
+
+impl Detection {
+    /// Start detecting, queueing the probe in `session` if necessary.
+    pub(crate) fn start(session: &mut ClientSession) -> Self {
+        if session.auth_preamble_received() {
+            return Detection::Done(ServerProfile::Source);
+        }
+
+        let probe = session.command_with_terminator(b"", Arc::new(SinglePacket));
+
+        Detection::Probing { probe }
+    }
+
+    /// The ids of the commands whose replies are awaited.
+    pub(crate) fn awaited(&self) -> Vec<i32> {
+        match *self {
+            Detection::Probing { probe } => vec![probe],
+            Detection::Confirming { probe, version } => vec![probe, version],
+            Detection::Done(_) => Vec::new(),
+        }
+    }
+
+    /// Continue with the reply to the command with the id `id`.
+    pub(crate) fn on_reply(self, session: &mut ClientSession, id: i32, reply: &[u8]) -> Self {
+        match self {
+            Detection::Probing { .. } => {
+                Detection::Done(ServerProfile::from_empty_command_reply(reply))
+            }
+            Detection::Confirming { probe, version } => {
+                session.cancel(probe);
+                session.cancel(version);
+
+                Detection::Done(if id == version {
+                    ServerProfile::from_version_reply(reply)
+                } else {
+                    ServerProfile::from_empty_command_reply(reply)
+                })
+            }
+            done => done,
+        }
+    }
+
+    /// Continue after the awaited replies did not arrive in time.
+    pub(crate) fn on_timeout(self, session: &mut ClientSession) -> Self {
+        match self {
+            Detection::Probing { probe } => {
+                let version = session
+                    .command_with_terminator(FACTORIO_VERSION_COMMAND, Arc::new(SinglePacket));
+
+                Detection::Confirming { probe, version }
+            }
+            Detection::Confirming { probe, version } => {
+                session.cancel(probe);
+                session.cancel(version);
+
+                Detection::Done(ServerProfile::Generic)
+            }
+            done => done,
+        }
+    }
+}
@@ -12,18 +12,38 @@
 //!
 //! - `rt-tokio`: Enable integration with the [Tokio](tokio) asynchronous runtime.
 //! - `rt-async-std`: Enable integration with the [async-std](async_std) asynchronous runtime.
+//! - `codec`: Enable [`RconCodec`] for use with [`tokio_util::codec::Framed`].
+//! - `chat`: Enable the [`chat`] module for building Minecraft JSON text components.
+//! - `config`: Enable the [`config`] module for named servers in a TOML config file.
+//! - `cli`: Build the `rcon` command line client, with an interactive prompt and a
+//!   one-shot mode.
+//! - `testing`: Enable the [`testing`] module with a scriptable mock server.
+//! - `proxy`: Build the `rcon-proxy` binary, which lets many rcon clients share a single
+//!   connection to a game server.
+//!
+//! # Sans-IO
+//!
+//! The protocol logic is available without any IO through [`ClientSession`] and
+//! [`PacketDecoder`], so rcon can also be driven from a custom event loop or from
+//! synchronous code.
+//!
+//! A ready-made synchronous client is available in the [`blocking`] module.
+//!
+//! Minecraft formatting codes in responses can be parsed and rendered for terminals
+//! and web pages with the [`formatting`] module.
 #![cfg_attr(doc_cfg, feature(doc_cfg))]
 
-use err_derive::Error;
-use packet::{Packet, PacketType};
 use std::fmt::{self, Debug, Formatter};
-use std::future::Future;
+use std::future::{poll_fn, Future};
 use std::io;
 use std::marker::PhantomData;
-use std::pin::Pin;
+use std::pin::{pin, Pin};
 use std::sync::Arc;
+use std::task::Poll;
 use std::time::Duration;
-use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::handshake::{CommandSettings, Detection};
 
 #[cfg(feature = "rt-async-std")]
 mod rt_async_std;
@@ -33,30 +53,63 @@ pub use rt_async_std::AsyncStdStream;
 #[cfg(feature = "rt-tokio")]
 mod rt_tokio;
 
+pub mod blocking;
+mod charset;
+#[cfg(feature = "chat")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "chat")))]
+pub mod chat;
+mod client;
+mod codec;
+#[cfg(feature = "config")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "config")))]
+pub mod config;
+mod error;
+pub mod formatting;
+mod handshake;
 mod packet;
-
-const INITIAL_PACKET_ID: i32 = 1;
-const DELAY_TIME_MILLIS: u64 = 3;
-const MINECRAFT_MAX_PAYLOAD_SIZE: usize = 1413;
-
-#[derive(Debug, Error)]
-pub enum Error {
-    #[error(display = "authentication failed")]
-    Auth,
-    #[error(display = "command exceeds the maximum length")]
-    CommandTooLong,
-    #[error(display = "{}", _0)]
-    Io(#[error(source)] io::Error),
-}
-
-pub type Result<T> = std::result::Result<T, Error>;
+mod pool;
+mod profile;
+mod reconnect;
+pub mod server;
+mod session;
+mod terminator;
+#[cfg(feature = "testing")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "testing")))]
+pub mod testing;
+
+pub use charset::Charset;
+pub use client::{Client, Driver};
+#[cfg(feature = "codec")]
+pub use codec::RconCodec;
+pub use codec::{PacketDecoder, DEFAULT_MAX_PACKET_SIZE};
+pub use error::{Error, ParseNameError, Result};
+pub use packet::{Packet, PacketType};
+pub use pool::{Pool, PoolConfig, PooledConnection};
+pub use profile::{ProfileSettings, ServerProfile, MINECRAFT_MAX_PAYLOAD_SIZE};
+pub use reconnect::{Backoff, ReconnectingConnection};
+pub use session::{ClientSession, Event};
+pub use terminator::{
+    EndMarker, IdleTimeout, KnownSuffix, PacketAction, ResponseState, ResponseTerminator,
+    SinglePacket, SourceMirror,
+};
+
+const READ_BUFFER_SIZE: usize = 4096;
+/// How long to wait for the reply to the probe sent by [`Builder::auto_detect`].
+const DETECT_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
 
 pub struct Connection<T> {
     io: T,
-    next_packet_id: i32,
-    minecraft_quirks_enabled: bool,
-    factorio_quirks_enabled: bool,
+    session: ClientSession,
+    profile: ServerProfile,
+    settings: CommandSettings,
+    unexpected_packets: UnexpectedPacketPolicy,
+    command_timeout: Option<Duration>,
     sleep_fn: SleepFn,
+    /// Whether a write was interrupted, possibly leaving part of a packet on the
+    /// transport.
+    broken: bool,
+    /// Whether the last command failed or was cancelled after it was queued.
+    interrupted: bool,
 }
 
 impl<T: AsyncRead + AsyncWrite + Unpin> Connection<T> {
@@ -72,113 +125,202 @@ impl<T: AsyncRead + AsyncWrite + Unpin> Connection<T> {
     /// runtime, or running rcon over a transport other than TCP. You generally will want to use
     /// one of the higher-level `connect` methods.
     ///
-    /// By default this enables Minecraft quirks.
+    /// By default this uses the [Minecraft profile](ServerProfile::Minecraft).
     /// If you need to customize this behaviour, use a [`Builder`].
     ///
-    /// This method requires one of the runtime features to be activated so that Minecraft quirks
-    /// mode is able to asynchronously sleep. If you want to provide a custom sleep function, see
-    /// [`Builder::sleep_fn`].
+    /// This method requires one of the runtime features to be activated so that the Minecraft
+    /// profile is able to asynchronously sleep. If you want to provide a custom sleep function,
+    /// see [`Builder::sleep_fn`].
     #[cfg(any(feature = "rt-tokio", feature = "rt-async-std"))]
     #[cfg_attr(doc_cfg, doc(cfg(any(feature = "rt-tokio", feature = "rt-async-std"))))]
     pub async fn handshake(io: T, password: &str) -> Result<Self> {
         Self::builder()
-            .enable_minecraft_quirks(true)
+            .profile(ServerProfile::Minecraft)
             .handshake(io, password)
             .await
     }
 
+    /// The server profile used by this connection.
+    pub fn profile(&self) -> &ServerProfile {
+        &self.profile
+    }
+
+    /// Execute a command and return its response, decoded with the [configured
+    /// charset](Builder::charset).
     pub async fn cmd(&mut self, cmd: &str) -> Result<String> {
-        if self.minecraft_quirks_enabled && cmd.len() > MINECRAFT_MAX_PAYLOAD_SIZE {
-            return Err(Error::CommandTooLong);
-        }
+        let body = self.cmd_bytes(cmd).await?;
 
-        self.send(PacketType::ExecCommand, cmd).await?;
+        self.settings.charset.decode(body)
+    }
 
-        if self.minecraft_quirks_enabled {
-            self.sleep_fn
-                .call(Duration::from_millis(DELAY_TIME_MILLIS))
-                .await;
-        }
+    /// Execute a command and return its response, failing with [`Error::Timeout`] if the
+    /// response takes longer than `timeout`.
+    ///
+    /// This overrides the [command timeout](Builder::command_timeout) of the connection.
+    pub async fn cmd_with_timeout(&mut self, cmd: &str, timeout: Duration) -> Result<String> {
+        let body = self.cmd_bytes_with_timeout(cmd, Some(timeout)).await?;
 
-        let response = self.receive_response().await?;
+        self.settings.charset.decode(body)
+    }
 
-        Ok(response)
+    /// Execute a command and return the raw bytes of its response.
+    ///
+    /// The command is still encoded with the [configured charset](Builder::charset).
+    pub async fn cmd_bytes(&mut self, cmd: &str) -> Result<Vec<u8>> {
+        self.cmd_bytes_with_timeout(cmd, self.command_timeout).await
     }
 
-    async fn receive_response(&mut self) -> Result<String> {
-        if self.factorio_quirks_enabled {
-            self.receive_single_packet_response().await
-        } else {
-            self.receive_multi_packet_response().await
+    async fn cmd_bytes_with_timeout(
+        &mut self,
+        cmd: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<u8>> {
+        if self.broken {
+            return Err(Error::Closed);
         }
-    }
 
-    async fn receive_single_packet_response(&mut self) -> Result<String> {
-        let received_packet = self.receive_packet().await?;
+        let cmd = self.settings.encode(cmd)?;
+
+        // stay set if the future is dropped before the command was written or its
+        // response arrived
+        self.broken = true;
+        self.interrupted = true;
+
+        let id = self.session.command(cmd);
+        let mut sleep_fn = self.sleep_fn.clone();
 
-        Ok(received_packet.get_body().into())
+        let response = async {
+            self.flush().await?;
+            self.broken = false;
+            self.wait_for_response(id).await
+        };
+
+        let result = match sleep_fn.limit(timeout, response).await {
+            Some(Err(err @ Error::UnexpectedPacket { .. })) => {
+                self.session.cancel(id);
+                Err(err)
+            }
+            Some(response) => response,
+            None => {
+                // the connection stays usable if the command has been written, a late
+                // response is discarded
+                self.session.cancel(id);
+                Err(Error::Timeout)
+            }
+        };
+
+        self.interrupted = result.is_err();
+        result
     }
 
-    async fn receive_multi_packet_response(&mut self) -> Result<String> {
-        // the server processes packets in order, so send an empty packet and
-        // remember its id to detect the end of a multi-packet response
-        let end_id = self.send(PacketType::ExecCommand, "").await?;
+    async fn auth(&mut self, password: &str) -> Result<()> {
+        let password = self.settings.encode_password(password)?;
 
-        let mut result = String::new();
+        self.session.auth(password);
+        self.flush().await?;
 
         loop {
-            let received_packet = self.receive_packet().await?;
-
-            if received_packet.get_id() == end_id {
-                // This is the response to the end-marker packet
-                return Ok(result);
+            match self.next_event().await? {
+                Event::Authenticated => return Ok(()),
+                Event::AuthFailed => return Err(Error::Auth),
+                // there is no command to fail during the handshake
+                event => UnexpectedPacketPolicy::Skip.check(&event)?,
             }
-
-            result += received_packet.get_body();
         }
     }
 
-    async fn auth(&mut self, password: &str) -> Result<()> {
-        self.send(PacketType::Auth, password).await?;
-        let received_packet = loop {
-            let received_packet = self.receive_packet().await?;
-            if received_packet.get_type() == PacketType::AuthResponse {
-                break received_packet;
+    /// Guess the server implementation after a successful authentication, see
+    /// [`Detection`].
+    async fn detect_profile(&mut self) -> Result<ServerProfile> {
+        let mut detection = Detection::start(&mut self.session);
+        let mut sleep_fn = self.sleep_fn.clone();
+
+        loop {
+            if let Detection::Done(profile) = detection {
+                return Ok(profile);
             }
-        };
 
-        if received_packet.is_error() {
-            Err(Error::Auth)
-        } else {
-            Ok(())
+            let awaited = detection.awaited();
+            let reply = async {
+                self.flush().await?;
+                self.wait_for_any(&awaited).await
+            };
+
+            detection = match sleep_fn.timeout(DETECT_PROBE_TIMEOUT, reply).await {
+                Some(reply) => {
+                    let (id, reply) = reply?;
+                    detection.on_reply(&mut self.session, id, &reply)
+                }
+                None => detection.on_timeout(&mut self.session),
+            };
         }
     }
 
-    async fn send(&mut self, ptype: PacketType, body: &str) -> io::Result<i32> {
-        let id = self.generate_packet_id();
+    async fn wait_for_response(&mut self, id: i32) -> Result<Vec<u8>> {
+        Ok(self.wait_for_any(&[id]).await?.1)
+    }
 
-        let packet = Packet::new(id, ptype, body.into());
+    /// Wait for the first response to one of the commands with the given ids.
+    async fn wait_for_any(&mut self, ids: &[i32]) -> Result<(i32, Vec<u8>)> {
+        loop {
+            let event = self.next_event().await?;
+            self.check_event(&event)?;
 
-        packet.serialize(&mut self.io).await?;
+            if let Event::Response { id, body } = event {
+                if ids.contains(&id) {
+                    return Ok((id, body));
+                }
+            }
+        }
+    }
 
-        Ok(id)
+    /// Apply the [unexpected packet policy](Builder::unexpected_packet_policy) to an event.
+    fn check_event(&self, event: &Event) -> Result<()> {
+        self.unexpected_packets.check(event)
     }
 
-    async fn receive_packet(&mut self) -> io::Result<Packet> {
-        Packet::deserialize(&mut self.io).await
+    /// Write all packets queued in the session to the transport.
+    async fn flush(&mut self) -> io::Result<()> {
+        for (delay, packet) in self.settings.transmits(&mut self.session) {
+            if let Some(delay) = delay {
+                self.sleep_fn.call(delay).await;
+            }
+
+            self.io.write_all(&packet).await?;
+        }
+
+        Ok(())
     }
 
-    fn generate_packet_id(&mut self) -> i32 {
-        let id = self.next_packet_id;
+    async fn next_event(&mut self) -> Result<Event> {
+        let mut buf = [0u8; READ_BUFFER_SIZE];
+
+        loop {
+            if let Some(event) = self.session.poll_event() {
+                return Ok(event);
+            }
 
-        // only use positive ids as the server uses negative ids to signal
-        // a failed authentication request
-        self.next_packet_id = self
-            .next_packet_id
-            .checked_add(1)
-            .unwrap_or(INITIAL_PACKET_ID);
+            let read = match self.session.idle_timeout() {
+                Some(idle_timeout) => {
+                    let read = self.io.read(&mut buf);
+
+                    match self.sleep_fn.timeout(idle_timeout, read).await {
+                        Some(read) => read?,
+                        None => {
+                            self.session.handle_idle();
+                            continue;
+                        }
+                    }
+                }
+                None => self.io.read(&mut buf).await?,
+            };
+
+            if read == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
 
-        id
+            self.session.receive(&buf[..read])?;
+        }
     }
 }
 
@@ -210,6 +352,18 @@ impl Debug for SleepFn {
 type CustomSleepFn =
     Arc<dyn Fn(Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
 
+/// Establishes a new transport, for connections that outlive a single transport.
+type ConnectFn<T> =
+    Arc<dyn Fn() -> Pin<Box<dyn Future<Output = io::Result<T>> + Send>> + Send + Sync>;
+
+fn connect_fn<T, F, Fut>(connect: F) -> ConnectFn<T>
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = io::Result<T>> + Send + 'static,
+{
+    Arc::new(move || Box::pin(connect()))
+}
+
 impl SleepFn {
     async fn call(&mut self, duration: Duration) {
         match self {
@@ -222,12 +376,79 @@ impl SleepFn {
             Self::Custom(f) => f(duration).await,
         }
     }
+
+    /// Like [`timeout`](Self::timeout), but without a limit if `duration` is `None`.
+    async fn limit<F: Future>(
+        &mut self,
+        duration: Option<Duration>,
+        future: F,
+    ) -> Option<F::Output> {
+        match duration {
+            Some(duration) => self.timeout(duration, future).await,
+            None => Some(future.await),
+        }
+    }
+
+    /// Run `future` to completion, unless `duration` elapses first.
+    async fn timeout<F: Future>(&mut self, duration: Duration, future: F) -> Option<F::Output> {
+        let mut sleep = pin!(self.call(duration));
+        let mut future = pin!(future);
+
+        poll_fn(|cx| {
+            if let Poll::Ready(output) = future.as_mut().poll(cx) {
+                return Poll::Ready(Some(output));
+            }
+
+            sleep.as_mut().poll(cx).map(|()| None)
+        })
+        .await
+    }
+}
+
+/// What to do with packets that do not belong to any pending request.
+///
+/// Packets of commands that have already completed, e.g. the rest of a response
+/// truncated by [`SinglePacket`], are always discarded silently. Unexpected packets
+/// received during the handshake are always skipped.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UnexpectedPacketPolicy {
+    /// Fail the current command with [`Error::UnexpectedPacket`].
+    ///
+    /// A [`Client`] runs many commands at once, so it only fails the command the
+    /// packet's id belongs to, and skips packets with unknown ids.
+    Error,
+    /// Log a warning with the [`log`] crate and discard the packet.
+    #[default]
+    Skip,
+}
+
+impl UnexpectedPacketPolicy {
+    /// Apply the policy to an event of the session.
+    fn check(self, event: &Event) -> Result<()> {
+        if let Event::UnexpectedPacket { id, ptype } = *event {
+            match self {
+                UnexpectedPacketPolicy::Error => return Err(Error::UnexpectedPacket { id, ptype }),
+                UnexpectedPacketPolicy::Skip => {
+                    log::warn!("skipping unexpected {:?} packet with id {}", ptype, id)
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
 pub struct Builder<T> {
-    minecraft_quirks_enabled: bool,
-    factorio_quirks_enabled: bool,
+    profile: ServerProfile,
+    max_packet_size: usize,
+    charset: Option<Charset>,
+    response_terminator: Option<Arc<dyn ResponseTerminator>>,
+    auto_detect: bool,
+    unexpected_packets: UnexpectedPacketPolicy,
+    connect_timeout: Option<Duration>,
+    auth_timeout: Option<Duration>,
+    command_timeout: Option<Duration>,
     sleep_fn: SleepFn,
     _io: PhantomData<fn() -> T>,
 }
@@ -242,8 +463,15 @@ impl<T> Default for Builder<T> {
         let sleep_fn = SleepFn::None;
 
         Self {
-            minecraft_quirks_enabled: false,
-            factorio_quirks_enabled: false,
+            profile: ServerProfile::Generic,
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            charset: None,
+            response_terminator: None,
+            auto_detect: false,
+            unexpected_packets: UnexpectedPacketPolicy::default(),
+            connect_timeout: None,
+            auth_timeout: None,
+            command_timeout: None,
             sleep_fn,
             _io: PhantomData,
         }
@@ -253,8 +481,15 @@ impl<T> Default for Builder<T> {
 impl<T> Clone for Builder<T> {
     fn clone(&self) -> Self {
         Self {
-            minecraft_quirks_enabled: self.minecraft_quirks_enabled,
-            factorio_quirks_enabled: self.factorio_quirks_enabled,
+            profile: self.profile.clone(),
+            max_packet_size: self.max_packet_size,
+            charset: self.charset,
+            response_terminator: self.response_terminator.clone(),
+            auto_detect: self.auto_detect,
+            unexpected_packets: self.unexpected_packets,
+            connect_timeout: self.connect_timeout,
+            auth_timeout: self.auth_timeout,
+            command_timeout: self.command_timeout,
             sleep_fn: self.sleep_fn.clone(),
             _io: PhantomData,
         }
@@ -266,31 +501,117 @@ impl<T> Builder<T> {
         Self::default()
     }
 
-    /// This enables the following quirks for Minecraft:
+    /// Select the quirks of the server implementation to connect to.
+    ///
+    /// Defaults to [`ServerProfile::Generic`]. The charset and response terminator of the
+    /// profile can be overridden with [`charset`](Self::charset) and
+    /// [`response_terminator`](Self::response_terminator).
+    pub fn profile(mut self, profile: ServerProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Shorthand for selecting the [Minecraft profile](ServerProfile::Minecraft).
+    ///
+    /// Disabling Minecraft quirks switches back to the [generic profile](ServerProfile::Generic)
+    /// if the Minecraft profile was selected.
+    pub fn enable_minecraft_quirks(self, value: bool) -> Self {
+        self.toggle_profile(ServerProfile::Minecraft, value)
+    }
+
+    /// Shorthand for selecting the [Factorio profile](ServerProfile::Factorio).
+    ///
+    /// Disabling Factorio quirks switches back to the [generic profile](ServerProfile::Generic)
+    /// if the Factorio profile was selected.
+    pub fn enable_factorio_quirks(self, value: bool) -> Self {
+        self.toggle_profile(ServerProfile::Factorio, value)
+    }
+
+    /// Set the maximum size of a packet received from the server.
     ///
-    /// Commands are delayed by 3ms to reduce the chance of crashing the server.
-    /// See <https://bugs.mojang.com/browse/MC-72390>.
+    /// The size is the value of the packet's length field, i.e. everything after the length
+    /// field itself. Packets claiming to be larger are rejected with [`Error::PacketTooLarge`]
+    /// before their body is read, which protects against non-rcon services and hostile servers.
     ///
-    /// The command length is limited to 1413 bytes.
-    /// Tests have shown the server to not work reliably
-    /// with greater command lengths.
-    pub fn enable_minecraft_quirks(mut self, value: bool) -> Self {
-        self.minecraft_quirks_enabled = value;
+    /// Defaults to [`DEFAULT_MAX_PACKET_SIZE`].
+    pub fn max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = max_packet_size;
         self
     }
 
-    /// This enables the following quirks for Factorio:
+    /// Set how the end of multi-packet responses is detected.
     ///
-    /// Only single-packet responses are enabled.
-    /// Multi-packets appear to work differently than in other server implementations
-    /// (an empty packet gives no response).
-    pub fn enable_factorio_quirks(mut self, value: bool) -> Self {
-        self.factorio_quirks_enabled = value;
+    /// Defaults to the response terminator of the [profile](Self::profile).
+    pub fn response_terminator<R: ResponseTerminator + 'static>(mut self, terminator: R) -> Self {
+        self.response_terminator = Some(Arc::new(terminator));
         self
     }
 
-    /// Set a custom function to use for sleeping between requests when [Minecraft quirks mode is
-    /// enabled](Self::enable_minecraft_quirks).
+    /// Detect the server implementation during the handshake instead of using the
+    /// [profile](Self::profile) set on this builder.
+    ///
+    /// After authenticating, the server is probed with an empty command, which takes up
+    /// to two seconds on servers that do not answer it. Source dedicated servers are detected
+    /// without a probe. Servers that cannot be identified use the
+    /// [generic profile](ServerProfile::Generic). The detected profile is available as
+    /// [`Connection::profile`], and the [charset](Self::charset) and
+    /// [response terminator](Self::response_terminator) overrides still apply to it.
+    pub fn auto_detect(mut self) -> Self {
+        self.auto_detect = true;
+        self
+    }
+
+    /// Set what to do with packets that do not belong to any pending request,
+    /// such as packets with an unknown id or of an unexpected type.
+    ///
+    /// Defaults to [`UnexpectedPacketPolicy::Skip`].
+    pub fn unexpected_packet_policy(mut self, policy: UnexpectedPacketPolicy) -> Self {
+        self.unexpected_packets = policy;
+        self
+    }
+
+    /// Fail with [`Error::Timeout`] if establishing the TCP connection takes longer than
+    /// `timeout`.
+    ///
+    /// Only applies to the `connect` methods and to [reconnecting
+    /// connections](ReconnectingConnection). Defaults to no timeout.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail with [`Error::Timeout`] if the server does not answer the authentication
+    /// request within `timeout`.
+    ///
+    /// Defaults to no timeout.
+    pub fn auth_timeout(mut self, timeout: Duration) -> Self {
+        self.auth_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail commands with [`Error::Timeout`] if their response takes longer than `timeout`.
+    ///
+    /// The timeout includes writing the command. The connection stays usable after a
+    /// timeout, unless the command could not be written in time, in which case later
+    /// commands fail with [`Error::Closed`]. Defaults to no timeout, see
+    /// [`Connection::cmd_with_timeout`] for a timeout on individual commands.
+    pub fn command_timeout(mut self, timeout: Duration) -> Self {
+        self.command_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the charset used to encode commands and decode responses.
+    ///
+    /// Defaults to the charset of the [profile](Self::profile), which is strict
+    /// [UTF-8](Charset::Utf8) for most profiles. Use [`Connection::cmd_bytes`] to get
+    /// responses without any decoding.
+    pub fn charset(mut self, charset: Charset) -> Self {
+        self.charset = Some(charset);
+        self
+    }
+
+    /// Set a custom function to use for sleeping between requests when the [profile](Self::profile)
+    /// requires a delay, as the [Minecraft profile](ServerProfile::Minecraft) does.
     ///
     /// When either of the `rt-tokio` or `rt-async-std` feature flags is enabled, this library will
     /// default to using the runtime's native sleeping function. This can be used to override it,
@@ -301,16 +622,17 @@ impl<T> Builder<T> {
     /// Using [futures-timer](https://docs.rs/futures-timer) instead of Tokio's native timer:
     ///
     /// ```
+    /// # #[cfg(feature = "rt-tokio")]
+    /// # async fn doc() -> rcon::Result<()> {
     /// # use tokio::net::TcpStream;
-    /// # async {
     /// let connection = <rcon::Connection<TcpStream>>::builder()
-    ///     .enable_minecraft_quirks(true)
+    ///     .profile(rcon::ServerProfile::Minecraft)
     ///     .sleep_fn(futures_timer::Delay::new)
     ///     .connect("localhost:25575", "hunter2")
     ///     .await?;
     /// # drop(connection);
-    /// # rcon::Result::Ok(())
-    /// # };
+    /// # Ok(())
+    /// # }
     /// ```
     pub fn sleep_fn<F, Fut>(mut self, f: F) -> Self
     where
@@ -330,34 +652,140 @@ impl<T> Builder<T> {
     /// # Panics
     ///
     /// If neither of the `rt-tokio` or `rt-async-std` feature flags are activated, no [custom sleep
-    /// function](Self::sleep_fn) has been set and [Minecraft quirks](Self::enable_minecraft_quirks)
-    /// have been enabled, this function will panic as Minecraft quirks need some way to
-    /// asynchronously sleep.
+    /// function](Self::sleep_fn) has been set and the [profile](Self::profile) requires a delay
+    /// between commands, this function will panic as the delay needs some way to asynchronously
+    /// sleep. The same applies to [response terminators](Self::response_terminator) with an idle
+    /// timeout, to [auto-detection](Self::auto_detect) and to timeouts.
     pub async fn handshake(self, io: T, password: &str) -> Result<Connection<T>>
     where
         T: AsyncRead + AsyncWrite + Unpin,
     {
+        let (profile, settings) = self.initial_profile();
+
         #[cfg(not(any(feature = "rt-tokio", feature = "rt-async-std")))]
-        if self.minecraft_quirks_enabled && matches!(self.sleep_fn, SleepFn::None) {
+        if (self.auth_timeout.is_some() || self.command_timeout.is_some())
+            && matches!(self.sleep_fn, SleepFn::None)
+        {
             panic!(
                 "\
-                Minecraft quirks mode is enabled, but no runtime or custom sleep function has been \
-                set. Enable one of the `rt-tokio` or `rt-async-std` feature flags, or set a custom \
+                A timeout has been set, but no runtime or custom sleep function has been set. \
+                Enable one of the `rt-tokio` or `rt-async-std` feature flags, or set a custom \
                 sleep function with `rcon::Builder::sleep_fn`.\
             "
             );
         }
 
+        #[cfg(not(any(feature = "rt-tokio", feature = "rt-async-std")))]
+        if self.auto_detect && matches!(self.sleep_fn, SleepFn::None) {
+            panic!(
+                "\
+                Auto-detecting the server profile requires a timeout, but no runtime or custom \
+                sleep function has been set. Enable one of the `rt-tokio` or `rt-async-std` \
+                feature flags, or set a custom sleep function with `rcon::Builder::sleep_fn`.\
+            "
+            );
+        }
+
+        #[cfg(not(any(feature = "rt-tokio", feature = "rt-async-std")))]
+        if settings.command_delay.is_some() && matches!(self.sleep_fn, SleepFn::None) {
+            panic!(
+                "\
+                The server profile requires a delay between commands, but no runtime or custom \
+                sleep function has been set. Enable one of the `rt-tokio` or `rt-async-std` \
+                feature flags, or set a custom sleep function with `rcon::Builder::sleep_fn`.\
+            "
+            );
+        }
+
+        #[cfg(not(any(feature = "rt-tokio", feature = "rt-async-std")))]
+        if settings.response_terminator.idle_timeout().is_some()
+            && matches!(self.sleep_fn, SleepFn::None)
+        {
+            panic!(
+                "\
+                The response terminator uses an idle timeout, but no runtime or custom sleep \
+                function has been set. Enable one of the `rt-tokio` or `rt-async-std` feature \
+                flags, or set a custom sleep function with `rcon::Builder::sleep_fn`.\
+            "
+            );
+        }
+
+        let (session, settings) = self.session(settings);
+
         let mut conn = Connection {
             io,
-            next_packet_id: INITIAL_PACKET_ID,
-            minecraft_quirks_enabled: self.minecraft_quirks_enabled,
-            factorio_quirks_enabled: self.factorio_quirks_enabled,
-            sleep_fn: self.sleep_fn,
+            session,
+            profile,
+            settings,
+            unexpected_packets: self.unexpected_packets,
+            command_timeout: self.command_timeout,
+            sleep_fn: self.sleep_fn.clone(),
+            broken: false,
+            interrupted: false,
         };
 
-        conn.auth(password).await?;
+        let mut sleep_fn = self.sleep_fn.clone();
+
+        sleep_fn
+            .limit(self.auth_timeout, conn.auth(password))
+            .await
+            .ok_or(Error::Timeout)??;
+
+        if self.auto_detect {
+            let profile = conn.detect_profile().await?;
+
+            conn.settings = CommandSettings::apply(self.settings(&profile), &mut conn.session);
+            conn.profile = profile;
+        }
 
         Ok(conn)
     }
+
+    /// Establish the transport of a `connect` method, applying the
+    /// [connect timeout](Self::connect_timeout).
+    async fn connect_io<F, S>(&self, connect: F) -> Result<S>
+    where
+        F: Future<Output = io::Result<S>>,
+    {
+        let mut sleep_fn = self.sleep_fn.clone();
+
+        Ok(sleep_fn
+            .limit(self.connect_timeout, connect)
+            .await
+            .ok_or(Error::Timeout)??)
+    }
+
+    /// Establish a transport with `connect` and perform a handshake on it.
+    async fn connect_with(&self, connect: &ConnectFn<T>, password: &str) -> Result<Connection<T>>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        let io = self.connect_io(connect()).await?;
+
+        self.clone().handshake(io, password).await
+    }
+
+    fn toggle_profile(mut self, profile: ServerProfile, value: bool) -> Self {
+        if value {
+            self.profile = profile;
+        } else if std::mem::discriminant(&self.profile) == std::mem::discriminant(&profile) {
+            self.profile = ServerProfile::Generic;
+        }
+
+        self
+    }
+
+    /// The settings of `profile`, with the overrides of this builder applied.
+    fn settings(&self, profile: &ServerProfile) -> ProfileSettings {
+        let mut settings = profile.settings();
+
+        if let Some(terminator) = self.response_terminator.clone() {
+            settings.response_terminator = terminator;
+        }
+        if let Some(charset) = self.charset {
+            settings.charset = charset;
+        }
+
+        settings
+    }
 }
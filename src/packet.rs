@@ -7,6 +7,9 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use crate::codec::{check_length, check_terminator, read_i32, DEFAULT_MAX_PACKET_SIZE};
+use crate::{Error, Result};
+use std::convert::TryFrom;
 use std::io;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
@@ -20,7 +23,7 @@ pub enum PacketType {
 }
 
 impl PacketType {
-    fn to_i32(self) -> i32 {
+    pub fn to_i32(self) -> i32 {
         match self {
             PacketType::Auth => 3,
             PacketType::AuthResponse => 2,
@@ -46,13 +49,41 @@ pub struct Packet {
     length: i32,
     id: i32,
     ptype: PacketType,
-    body: String,
+    body: Vec<u8>,
+}
+
+/// The size of the fields following the length field, besides the body.
+const HEADER_AND_TERMINATOR: usize = 10;
+
+/// Fail with [`Error::PacketTooLarge`] if a body of `len` bytes does not fit into a
+/// packet, whose length field is an `i32`.
+pub(crate) fn check_body_len(len: usize) -> Result<()> {
+    let max = i32::MAX as usize;
+
+    match len.checked_add(HEADER_AND_TERMINATOR) {
+        Some(size) if size <= max => Ok(()),
+        size => Err(Error::PacketTooLarge {
+            size: size.unwrap_or(usize::MAX),
+            max,
+        }),
+    }
 }
 
 impl Packet {
-    pub fn new(id: i32, ptype: PacketType, body: String) -> Packet {
+    /// Create a packet.
+    ///
+    /// # Panics
+    ///
+    /// If the packet does not fit into its `i32` length field, i.e. if `body` is
+    /// 2 GiB or larger.
+    pub fn new(id: i32, ptype: PacketType, body: Vec<u8>) -> Packet {
+        let length = i32::try_from(body.len())
+            .ok()
+            .and_then(|len| len.checked_add(HEADER_AND_TERMINATOR as i32))
+            .expect("the packet body must be smaller than 2 GiB");
+
         Packet {
-            length: 10 + body.len() as i32,
+            length,
             id,
             ptype,
             body,
@@ -63,59 +94,70 @@ impl Packet {
         self.id < 0
     }
 
-    pub async fn serialize<T: Unpin + AsyncWrite>(&self, w: &mut T) -> io::Result<()> {
-        // Write bytes to a buffer first so only one tcp packet is sent
-        // This is done in order to not overwhelm a Minecraft server
-        let mut buf = Vec::with_capacity(self.length as usize);
-
+    /// Append the wire representation of this packet to `buf`.
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        buf.reserve(4 + self.length as usize);
         buf.extend_from_slice(&self.length.to_le_bytes());
         buf.extend_from_slice(&self.id.to_le_bytes());
         buf.extend_from_slice(&self.ptype.to_i32().to_le_bytes());
-        buf.extend_from_slice(self.body.as_bytes());
+        buf.extend_from_slice(&self.body);
         buf.extend_from_slice(&[0x00, 0x00]);
+    }
+
+    pub async fn serialize<T: Unpin + AsyncWrite>(&self, w: &mut T) -> io::Result<()> {
+        // Write bytes to a buffer first so only one tcp packet is sent
+        // This is done in order to not overwhelm a Minecraft server
+        let mut buf = Vec::new();
+
+        self.encode(&mut buf);
 
         w.write_all(&buf).await?;
 
         Ok(())
     }
 
-    pub async fn deserialize<T: Unpin + AsyncRead>(r: &mut T) -> io::Result<Packet> {
-        let mut buf = [0u8; 4];
+    /// Read a single packet, rejecting packets larger than [`DEFAULT_MAX_PACKET_SIZE`].
+    pub async fn deserialize<T: Unpin + AsyncRead>(r: &mut T) -> Result<Packet> {
+        Self::deserialize_with_max_size(r, DEFAULT_MAX_PACKET_SIZE).await
+    }
 
-        r.read_exact(&mut buf).await?;
-        let length = i32::from_le_bytes(buf);
-        r.read_exact(&mut buf).await?;
-        let id = i32::from_le_bytes(buf);
-        r.read_exact(&mut buf).await?;
-        let ptype = i32::from_le_bytes(buf);
-        let body_length = length - 10;
-        let mut body_buffer = Vec::with_capacity(body_length as usize);
+    /// Read a single packet, rejecting packets whose length field exceeds `max_packet_size`.
+    pub async fn deserialize_with_max_size<T: Unpin + AsyncRead>(
+        r: &mut T,
+        max_packet_size: usize,
+    ) -> Result<Packet> {
+        let mut header = [0u8; 12];
 
-        r.take(body_length as u64)
-            .read_to_end(&mut body_buffer)
-            .await?;
+        r.read_exact(&mut header).await?;
+        let length = check_length(read_i32(&header[0..4]), max_packet_size)?;
+        let id = read_i32(&header[4..8]);
+        let ptype = read_i32(&header[8..12]);
 
-        let body = String::from_utf8(body_buffer)
-            .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+        // the rest of the packet: body and terminating nulls
+        let mut rest = vec![0u8; length - 8];
+        r.read_exact(&mut rest).await?;
 
-        // terminating nulls
-        let mut buf = [0u8; 2];
-        r.read_exact(&mut buf).await?;
+        let terminator = rest.split_off(rest.len() - 2);
+        check_terminator(&terminator)?;
 
         let packet = Packet {
-            length,
+            length: length as i32,
             id,
             ptype: PacketType::from_i32(ptype, true),
-            body,
+            body: rest,
         };
 
         Ok(packet)
     }
 
-    pub fn get_body(&self) -> &str {
+    pub fn get_body(&self) -> &[u8] {
         &self.body
     }
 
+    pub fn into_body(self) -> Vec<u8> {
+        self.body
+    }
+
     pub fn get_type(&self) -> PacketType {
         self.ptype
     }
@@ -0,0 +1,355 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{Builder, ConnectFn, Connection, Result};
+
+/// The limits of a [`Pool`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolConfig {
+    /// The number of connections kept open by [`Pool::warm_up`] and idle eviction.
+    pub min_size: usize,
+    /// The maximum number of open connections, and therefore of concurrent commands.
+    ///
+    /// Minecraft servers are known to crash when flooded with rcon commands
+    /// (<https://bugs.mojang.com/browse/MC-72390>), so keep this low for them.
+    pub max_size: usize,
+    /// A cheap command executed on idle connections before handing them out.
+    /// Connections failing it are closed.
+    pub health_check: Option<String>,
+    /// Close connections that have been idle for this long, as long as more than
+    /// `min_size` connections are open.
+    pub idle_timeout: Option<Duration>,
+    /// Close connections once they have been open for this long.
+    pub max_lifetime: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 0,
+            max_size: 4,
+            health_check: None,
+            idle_timeout: Some(Duration::from_secs(10 * 60)),
+            max_lifetime: Some(Duration::from_secs(30 * 60)),
+        }
+    }
+}
+
+struct IdleConnection<T> {
+    conn: Connection<T>,
+    created: Instant,
+    idle_since: Instant,
+}
+
+struct Shared<T> {
+    builder: Builder<T>,
+    connect: ConnectFn<T>,
+    password: String,
+    config: PoolConfig,
+    idle: Mutex<Vec<IdleConnection<T>>>,
+    open: AtomicUsize,
+    permits: Arc<Semaphore>,
+}
+
+impl<T> Shared<T> {
+    fn close(&self, conn: Connection<T>) {
+        self.open.fetch_sub(1, Ordering::SeqCst);
+        drop(conn);
+    }
+}
+
+/// A connection taken out of the pool, which is closed when dropped unless it is
+/// kept.
+struct Closing<'a, T> {
+    conn: Option<Connection<T>>,
+    shared: &'a Shared<T>,
+}
+
+impl<T> Closing<'_, T> {
+    fn conn_mut(&mut self) -> &mut Connection<T> {
+        self.conn.as_mut().unwrap()
+    }
+
+    fn keep(mut self) -> Connection<T> {
+        self.conn.take().unwrap()
+    }
+}
+
+impl<T> Drop for Closing<'_, T> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.shared.close(conn);
+        }
+    }
+}
+
+/// A pool of authenticated connections to a single rcon server.
+///
+/// All connections share the configuration of the [`Builder`] the pool was created
+/// from. Expired connections are evicted and health checks run when a connection is
+/// [checked out](Self::get), so the pool does not need a background task.
+///
+/// Create one with [`Builder::pool_with`], or with `Builder::pool` when a runtime
+/// feature is enabled. Cloning a pool is cheap and shares its connections.
+pub struct Pool<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for Pool<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Debug for Pool<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("config", &self.shared.config)
+            .field("open", &self.open())
+            .field("idle", &self.idle())
+            .finish()
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Pool<T> {
+    /// Check out a connection, waiting while [`max_size`](PoolConfig::max_size)
+    /// connections are in use.
+    ///
+    /// Idle connections are reused if they pass the health check, otherwise a new
+    /// connection is established.
+    pub async fn get(&self) -> Result<PooledConnection<T>> {
+        let permit = self
+            .shared
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the semaphore is never closed");
+
+        while let Some(idle) = self.take_idle() {
+            // closes the connection if the check fails or `get` is cancelled during it
+            let mut checked = Closing {
+                conn: Some(idle.conn),
+                shared: &self.shared,
+            };
+
+            if let Some(health_check) = &self.shared.config.health_check {
+                if checked.conn_mut().cmd(health_check).await.is_err() {
+                    continue;
+                }
+            }
+
+            return Ok(self.pooled(checked.keep(), idle.created, permit));
+        }
+
+        let conn = self.open_connection().await?;
+
+        Ok(self.pooled(conn, Instant::now(), permit))
+    }
+
+    /// Open connections until [`min_size`](PoolConfig::min_size) connections are open.
+    ///
+    /// Opening a connection counts towards [`max_size`](PoolConfig::max_size) like
+    /// checking one out, so this stops early while the pool is fully in use.
+    pub async fn warm_up(&self) -> Result<()> {
+        while self.open() < self.shared.config.min_size {
+            let _permit = match self.shared.permits.clone().try_acquire_owned() {
+                Ok(permit) => permit,
+                Err(_) => break,
+            };
+            let conn = self.open_connection().await?;
+
+            self.shared.idle.lock().unwrap().push(IdleConnection {
+                conn,
+                created: Instant::now(),
+                idle_since: Instant::now(),
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn open_connection(&self) -> Result<Connection<T>> {
+        let shared = &self.shared;
+        let conn = shared
+            .builder
+            .connect_with(&shared.connect, &shared.password)
+            .await?;
+
+        shared.open.fetch_add(1, Ordering::SeqCst);
+
+        Ok(conn)
+    }
+}
+
+impl<T> Pool<T> {
+    pub(crate) fn new(
+        builder: Builder<T>,
+        connect: ConnectFn<T>,
+        password: &str,
+        config: PoolConfig,
+    ) -> Self {
+        assert!(
+            config.max_size > 0,
+            "the maximum size of a pool must be at least 1"
+        );
+        assert!(
+            config.min_size <= config.max_size,
+            "the minimum size of a pool ({}) must not exceed its maximum size ({})",
+            config.min_size,
+            config.max_size
+        );
+
+        Self {
+            shared: Arc::new(Shared {
+                builder,
+                connect,
+                password: password.to_owned(),
+                permits: Arc::new(Semaphore::new(config.max_size)),
+                config,
+                idle: Mutex::new(Vec::new()),
+                open: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// The number of open connections, both idle and checked out.
+    pub fn open(&self) -> usize {
+        self.shared.open.load(Ordering::SeqCst)
+    }
+
+    /// The number of idle connections.
+    pub fn idle(&self) -> usize {
+        self.shared.idle.lock().unwrap().len()
+    }
+
+    /// Close all expired idle connections and take the most recently used of the
+    /// remaining ones.
+    fn take_idle(&self) -> Option<IdleConnection<T>> {
+        let config = &self.shared.config;
+        let mut idle = self.shared.idle.lock().unwrap();
+        let now = Instant::now();
+        let mut index = 0;
+
+        // the least recently used connections come first, so they are evicted first
+        while index < idle.len() {
+            let candidate = &idle[index];
+            let lifetime_exceeded =
+                matches!(config.max_lifetime, Some(max) if now - candidate.created >= max);
+            let idle_too_long = matches!(config.idle_timeout, Some(max) if now - candidate.idle_since >= max)
+                && self.open() > config.min_size;
+
+            if lifetime_exceeded || idle_too_long {
+                self.shared.close(idle.remove(index).conn);
+            } else {
+                index += 1;
+            }
+        }
+
+        idle.pop()
+    }
+
+    fn pooled(
+        &self,
+        conn: Connection<T>,
+        created: Instant,
+        permit: OwnedSemaphorePermit,
+    ) -> PooledConnection<T> {
+        PooledConnection {
+            conn: Some(conn),
+            created,
+            shared: self.shared.clone(),
+            _permit: permit,
+        }
+    }
+}
+
+/// A connection checked out of a [`Pool`], which is returned to the pool when dropped.
+///
+/// Connections whose last command failed or was cancelled are closed instead, as
+/// they may still receive its response. Call [`discard`](Self::discard) to close a
+/// connection regardless.
+pub struct PooledConnection<T> {
+    conn: Option<Connection<T>>,
+    created: Instant,
+    shared: Arc<Shared<T>>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl<T> PooledConnection<T> {
+    /// Close the connection instead of returning it to the pool.
+    pub fn discard(mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.shared.close(conn);
+        }
+    }
+}
+
+impl<T> Deref for PooledConnection<T> {
+    type Target = Connection<T>;
+
+    fn deref(&self) -> &Connection<T> {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for PooledConnection<T> {
+    fn deref_mut(&mut self) -> &mut Connection<T> {
+        self.conn.as_mut().unwrap()
+    }
+}
+
+impl<T> Drop for PooledConnection<T> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            if conn.interrupted {
+                self.shared.close(conn);
+                return;
+            }
+
+            self.shared.idle.lock().unwrap().push(IdleConnection {
+                conn,
+                created: self.created,
+                idle_since: Instant::now(),
+            });
+        }
+    }
+}
+
+impl<T> Builder<T> {
+    /// Create a [`Pool`] of connections whose transports are established with `connect`.
+    ///
+    /// This is the runtime-agnostic counterpart of `Builder::pool`. No connection is
+    /// established until one is checked out, or until [`Pool::warm_up`] is called.
+    ///
+    /// # Panics
+    ///
+    /// If the [`max_size`](PoolConfig::max_size) of `config` is zero or less than its
+    /// [`min_size`](PoolConfig::min_size).
+    pub fn pool_with<F, Fut>(self, connect: F, password: &str, config: PoolConfig) -> Pool<T>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<T>> + Send + 'static,
+    {
+        Pool::new(self, crate::connect_fn(connect), password, config)
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::charset::Charset;
+use crate::terminator::{EndMarker, ResponseTerminator, SinglePacket, SourceMirror};
+use crate::ParseNameError;
+
+/// The maximum length in bytes of a command accepted by Minecraft servers.
+pub const MINECRAFT_MAX_PAYLOAD_SIZE: usize = 1413;
+const MINECRAFT_COMMAND_DELAY: Duration = Duration::from_millis(3);
+/// Answered by Factorio with its version, e.g. `1.1.110`.
+pub(crate) const FACTORIO_VERSION_COMMAND: &str = "/version";
+
+/// Replies to an empty command that identify a server implementation.
+const EMPTY_COMMAND_REPLIES: &[(&str, ServerProfile)] = &[
+    ("Unknown or incomplete command", ServerProfile::Minecraft),
+    // Minecraft before 1.13
+    ("Unknown command. Try /help", ServerProfile::Minecraft),
+    ("Server received, But no response", ServerProfile::Ark),
+];
+
+/// The names of the built-in profiles, as parsed by [`ServerProfile::from_str`].
+const PROFILE_NAMES: &[(&str, ServerProfile)] = &[
+    ("generic", ServerProfile::Generic),
+    ("minecraft", ServerProfile::Minecraft),
+    ("source", ServerProfile::Source),
+    ("factorio", ServerProfile::Factorio),
+    ("ark", ServerProfile::Ark),
+    ("palworld", ServerProfile::Palworld),
+    ("squad", ServerProfile::Squad),
+];
+
+/// The quirks of a particular rcon server implementation.
+///
+/// Every game implements rcon slightly differently. A profile bundles everything
+/// needed to talk to one of them, and is selected with [`Builder::profile`](crate::Builder::profile).
+///
+/// The built-in profiles can be parsed from their lowercase names, e.g. `"minecraft"`,
+/// which is useful for configuration and command line arguments.
+#[derive(Debug, Clone, Default)]
+pub enum ServerProfile {
+    /// Plain rcon as described by the Source protocol, without any quirks.
+    #[default]
+    Generic,
+    /// Minecraft: Java Edition.
+    ///
+    /// Packets are delayed by 3ms to reduce the chance of crashing the server.
+    /// See <https://bugs.mojang.com/browse/MC-72390>.
+    ///
+    /// The command length is limited to 1413 bytes.
+    /// Tests have shown the server to not work reliably
+    /// with greater command lengths.
+    Minecraft,
+    /// Source dedicated servers, e.g. Counter-Strike or Team Fortress 2.
+    ///
+    /// Responses are terminated with the [`SourceMirror`] strategy. The server also
+    /// sends an empty `ResponseValue` packet before the `AuthResponse`, which is
+    /// accepted with every profile and used to [detect](crate::Builder::auto_detect)
+    /// Source servers.
+    Source,
+    /// Factorio.
+    ///
+    /// Factorio does not answer empty packets, so the end of multi-packet responses is
+    /// detected by sending a `/version` command after each command instead.
+    /// Its response is discarded.
+    Factorio,
+    /// ARK: Survival Evolved.
+    ///
+    /// Every command is answered with a single packet.
+    Ark,
+    /// Palworld.
+    ///
+    /// Every command is answered with a single packet, and responses are not always
+    /// valid UTF-8, so they are decoded lossily.
+    Palworld,
+    /// Squad, which implements rcon like Source dedicated servers.
+    Squad,
+    /// Custom settings for servers not covered by the other profiles.
+    Custom(ProfileSettings),
+}
+
+impl ServerProfile {
+    /// The settings of this profile.
+    pub fn settings(&self) -> ProfileSettings {
+        let generic = ProfileSettings::default();
+
+        match self {
+            ServerProfile::Generic => generic,
+            ServerProfile::Minecraft => ProfileSettings {
+                max_command_len: Some(MINECRAFT_MAX_PAYLOAD_SIZE),
+                command_delay: Some(MINECRAFT_COMMAND_DELAY),
+                ..generic
+            },
+            ServerProfile::Source | ServerProfile::Squad => ProfileSettings {
+                response_terminator: Arc::new(SourceMirror),
+                ..generic
+            },
+            ServerProfile::Factorio => ProfileSettings {
+                response_terminator: Arc::new(EndMarker::new(FACTORIO_VERSION_COMMAND)),
+                ..generic
+            },
+            ServerProfile::Ark => ProfileSettings {
+                response_terminator: Arc::new(SinglePacket),
+                ..generic
+            },
+            ServerProfile::Palworld => ProfileSettings {
+                response_terminator: Arc::new(SinglePacket),
+                charset: Charset::Utf8Lossy,
+                ..generic
+            },
+            ServerProfile::Custom(settings) => settings.clone(),
+        }
+    }
+
+    /// Guess the server implementation from its reply to an empty command.
+    pub(crate) fn from_empty_command_reply(reply: &[u8]) -> Self {
+        EMPTY_COMMAND_REPLIES
+            .iter()
+            .find(|(needle, _)| {
+                reply
+                    .windows(needle.len())
+                    .any(|window| window == needle.as_bytes())
+            })
+            .map_or(ServerProfile::Generic, |(_, profile)| profile.clone())
+    }
+
+    /// Guess the server implementation from its reply to [`FACTORIO_VERSION_COMMAND`].
+    pub(crate) fn from_version_reply(reply: &[u8]) -> Self {
+        let version = String::from_utf8_lossy(reply);
+        let version = version.trim();
+
+        if version.contains('.') && version.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            ServerProfile::Factorio
+        } else {
+            Self::from_empty_command_reply(reply)
+        }
+    }
+}
+
+impl FromStr for ServerProfile {
+    type Err = ParseNameError;
+
+    fn from_str(name: &str) -> Result<Self, ParseNameError> {
+        PROFILE_NAMES
+            .iter()
+            .find(|(candidate, _)| candidate.eq_ignore_ascii_case(name))
+            .map(|(_, profile)| profile.clone())
+            .ok_or_else(|| ParseNameError::new("server profile", name))
+    }
+}
+
+/// The settings bundled by a [`ServerProfile`].
+///
+/// The defaults are those of [`ServerProfile::Generic`].
+#[derive(Debug, Clone)]
+pub struct ProfileSettings {
+    /// Commands longer than this many bytes are rejected with
+    /// [`Error::CommandTooLong`](crate::Error::CommandTooLong).
+    pub max_command_len: Option<usize>,
+    /// Delay between consecutive packets sent to the server.
+    pub command_delay: Option<Duration>,
+    /// How the end of multi-packet responses is detected.
+    pub response_terminator: Arc<dyn ResponseTerminator>,
+    /// The charset used for commands and responses.
+    pub charset: Charset,
+}
+
+impl Default for ProfileSettings {
+    fn default() -> Self {
+        Self {
+            max_command_len: None,
+            command_delay: None,
+            response_terminator: Arc::new(EndMarker::default()),
+            charset: Charset::Utf8,
+        }
+    }
+}
@@ -0,0 +1,356 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::{Builder, ConnectFn, Connection, Error, Result};
+
+type Callback = Arc<dyn Fn() + Send + Sync>;
+type ErrorCallback = Arc<dyn Fn(&Error) + Send + Sync>;
+
+/// How long to wait between attempts to reconnect.
+///
+/// The delay starts at `initial` and is multiplied by `multiplier` after every failed
+/// attempt, up to `max`. Each delay is then shortened by a random fraction of up to
+/// `jitter`, so that many clients do not reconnect in lockstep after a server restart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backoff {
+    /// The delay after the first failed attempt.
+    pub initial: Duration,
+    /// The upper bound of the delay.
+    pub max: Duration,
+    /// The factor the delay grows by after every failed attempt, at least `1.0`.
+    pub multiplier: f64,
+    /// The maximum fraction of the delay removed at random, between `0.0` and `1.0`.
+    pub jitter: f64,
+    /// Give up after this many failed attempts in a row, or never if `None`.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_attempts: Some(10),
+        }
+    }
+}
+
+impl Backoff {
+    /// The delay after `attempt` failed attempts, before jitter is applied.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        if self.initial.is_zero() {
+            return Duration::ZERO;
+        }
+
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self.initial.as_secs_f64() * self.multiplier.powi(exponent);
+
+        // the delay grows beyond what a `Duration` can hold after enough attempts
+        Duration::try_from_secs_f64(delay).map_or(self.max, |delay| delay.min(self.max))
+    }
+}
+
+/// A connection that transparently reconnects and re-authenticates when it breaks.
+///
+/// Game servers restart, crash or drop rcon clients on map changes. This wrapper
+/// remembers how to connect, detects broken connections (EOF, connection resets,
+/// timeouts, corrupt packets) and reconnects with [exponential backoff](Backoff)
+/// the next time a command is executed.
+///
+/// Commands that fail because the connection broke are not retried by
+/// [`cmd`](Self::cmd), as the server might have executed them already. Use
+/// [`cmd_idempotent`](Self::cmd_idempotent) for commands that are safe to repeat.
+///
+/// Create one with [`Builder::reconnect_with`], or with `Builder::reconnecting`
+/// when a runtime feature is enabled.
+pub struct ReconnectingConnection<T> {
+    builder: Builder<T>,
+    password: String,
+    connect: ConnectFn<T>,
+    conn: Option<Connection<T>>,
+    backoff: Backoff,
+    max_retries: u32,
+    ever_connected: bool,
+    rng: XorShift,
+    on_connected: Option<Callback>,
+    on_disconnected: Option<ErrorCallback>,
+    on_reauthenticated: Option<Callback>,
+}
+
+impl<T> Debug for ReconnectingConnection<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReconnectingConnection")
+            .field("connected", &self.conn.is_some())
+            .field("backoff", &self.backoff)
+            .field("max_retries", &self.max_retries)
+            .finish()
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> ReconnectingConnection<T> {
+    pub(crate) fn new(builder: Builder<T>, connect: ConnectFn<T>, password: &str) -> Self {
+        Self {
+            builder,
+            password: password.to_owned(),
+            connect,
+            conn: None,
+            backoff: Backoff::default(),
+            max_retries: 3,
+            ever_connected: false,
+            rng: XorShift::seeded(),
+            on_connected: None,
+            on_disconnected: None,
+            on_reauthenticated: None,
+        }
+    }
+
+    /// Set the delays between attempts to reconnect.
+    ///
+    /// Defaults to [`Backoff::default`].
+    ///
+    /// # Panics
+    ///
+    /// If the multiplier of `backoff` is less than `1.0` or not finite, or its jitter
+    /// is not between `0.0` and `1.0`.
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        assert!(
+            backoff.multiplier.is_finite() && backoff.multiplier >= 1.0,
+            "the backoff multiplier must be a finite number of at least 1.0, got {}",
+            backoff.multiplier
+        );
+        assert!(
+            (0.0..=1.0).contains(&backoff.jitter),
+            "the backoff jitter must be between 0.0 and 1.0, got {}",
+            backoff.jitter
+        );
+
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set how often [`cmd_idempotent`](Self::cmd_idempotent) retries a command after the
+    /// connection broke.
+    ///
+    /// Defaults to 3.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Call `f` whenever a connection has been established and authenticated.
+    pub fn on_connected<F: Fn() + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.on_connected = Some(Arc::new(f));
+        self
+    }
+
+    /// Call `f` with the error that broke the connection.
+    pub fn on_disconnected<F: Fn(&Error) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.on_disconnected = Some(Arc::new(f));
+        self
+    }
+
+    /// Call `f` whenever the connection has been re-established after it broke.
+    ///
+    /// Unlike [`on_connected`](Self::on_connected), this is not called for the
+    /// first connection.
+    pub fn on_reauthenticated<F: Fn() + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.on_reauthenticated = Some(Arc::new(f));
+        self
+    }
+
+    /// Whether a connection is currently established.
+    pub fn is_connected(&self) -> bool {
+        self.conn.is_some()
+    }
+
+    /// Connect to the server unless a connection is already established.
+    ///
+    /// Failed attempts are repeated according to the [backoff](Self::backoff). A rejected
+    /// password fails immediately with [`Error::Auth`].
+    pub async fn connect(&mut self) -> Result<&mut Connection<T>> {
+        if self.conn.is_none() {
+            let conn = self.reconnect().await?;
+            self.conn = Some(conn);
+        }
+
+        Ok(self.conn.as_mut().unwrap())
+    }
+
+    /// Drop the current connection, if any. The next command reconnects.
+    pub fn disconnect(&mut self) {
+        self.conn = None;
+    }
+
+    /// Execute a command, connecting first if necessary.
+    ///
+    /// If the connection breaks while executing the command, the error is returned and
+    /// the next command reconnects.
+    pub async fn cmd(&mut self, cmd: &str) -> Result<String> {
+        let result = self.connect().await?.cmd(cmd).await;
+
+        self.check(result)
+    }
+
+    /// Execute a command that is safe to repeat, reconnecting and retrying it up to
+    /// [`max_retries`](Self::max_retries) times if the connection breaks.
+    pub async fn cmd_idempotent(&mut self, cmd: &str) -> Result<String> {
+        let mut retries = 0;
+
+        loop {
+            // failing to reconnect is not retried, the backoff has already given up
+            let result = self.connect().await?.cmd(cmd).await;
+
+            match self.check(result) {
+                Err(err) if breaks_connection(&err) && retries < self.max_retries => retries += 1,
+                result => return result,
+            }
+        }
+    }
+
+    fn check<R>(&mut self, result: Result<R>) -> Result<R> {
+        if let Err(err) = &result {
+            if breaks_connection(err) {
+                self.conn = None;
+
+                if let Some(on_disconnected) = &self.on_disconnected {
+                    on_disconnected(err);
+                }
+            }
+        }
+
+        result
+    }
+
+    async fn reconnect(&mut self) -> Result<Connection<T>> {
+        let mut attempt = 0;
+
+        loop {
+            match self
+                .builder
+                .connect_with(&self.connect, &self.password)
+                .await
+            {
+                Ok(conn) => {
+                    if let Some(on_connected) = &self.on_connected {
+                        on_connected();
+                    }
+                    if self.ever_connected {
+                        if let Some(on_reauthenticated) = &self.on_reauthenticated {
+                            on_reauthenticated();
+                        }
+                    }
+                    self.ever_connected = true;
+
+                    return Ok(conn);
+                }
+                Err(Error::Auth) => return Err(Error::Auth),
+                Err(err) => {
+                    attempt += 1;
+
+                    if matches!(self.backoff.max_attempts, Some(max) if attempt >= max) {
+                        return Err(err);
+                    }
+                }
+            }
+
+            let delay = self.backoff.delay(attempt);
+            let jitter = delay.mul_f64(self.backoff.jitter * self.rng.next_f64());
+
+            self.builder.sleep_fn.call(delay - jitter).await;
+        }
+    }
+}
+
+impl<T> Builder<T> {
+    /// Create a [`ReconnectingConnection`] that establishes its transport with `connect`.
+    ///
+    /// This is the runtime-agnostic counterpart of `Builder::reconnecting`. No connection
+    /// is established until the first command, or until
+    /// [`ReconnectingConnection::connect`] is called.
+    ///
+    /// # Panics
+    ///
+    /// If neither of the `rt-tokio` or `rt-async-std` feature flags are activated and no
+    /// [custom sleep function](Self::sleep_fn) has been set, this function will panic as
+    /// the backoff needs some way to asynchronously sleep.
+    pub fn reconnect_with<F, Fut>(self, connect: F, password: &str) -> ReconnectingConnection<T>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<T>> + Send + 'static,
+    {
+        #[cfg(not(any(feature = "rt-tokio", feature = "rt-async-std")))]
+        if matches!(self.sleep_fn, crate::SleepFn::None) {
+            panic!(
+                "\
+                Reconnecting requires a delay between attempts, but no runtime or custom sleep \
+                function has been set. Enable one of the `rt-tokio` or `rt-async-std` feature \
+                flags, or set a custom sleep function with `rcon::Builder::sleep_fn`.\
+            "
+            );
+        }
+
+        ReconnectingConnection::new(self, crate::connect_fn(connect), password)
+    }
+}
+
+/// Whether the connection can no longer be used after this error.
+fn breaks_connection(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::Io(_)
+            | Error::Timeout
+            | Error::PacketTooLarge { .. }
+            | Error::MalformedPacket(_)
+            | Error::UnexpectedPacket { .. }
+    )
+}
+
+/// A tiny PRNG for backoff jitter and fault injection, which do not need to be
+/// unpredictable.
+#[derive(Debug, Clone)]
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    fn seeded() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.subsec_nanos());
+
+        Self::new(u64::from(nanos))
+    }
+
+    /// A generator producing the same sequence for the same `seed`.
+    pub(crate) fn new(seed: u64) -> Self {
+        // spread small seeds over all bits, and the state must never be zero
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number in `0.0..1.0`.
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
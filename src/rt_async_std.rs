@@ -1,22 +1,25 @@
 use async_std::io::{Read, Write};
 use async_std::net::{TcpStream, ToSocketAddrs};
 use async_std::task::ready;
+use std::future::Future;
 use std::io::{self, IoSlice};
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio::io::{AsyncRead as TokioRead, AsyncWrite as TokioWrite, ReadBuf};
 
-use crate::{Builder, Connection, Result};
+use crate::server::{Accept, Listener};
+use crate::{Builder, Connection, Pool, PoolConfig, ReconnectingConnection, Result, ServerProfile};
 
 impl Connection<AsyncStdStream> {
     /// Connect to an rcon server using the [async-std](async_std) runtime.
     ///
-    /// By default this enables Minecraft quirks.
+    /// By default this uses the [Minecraft profile](crate::ServerProfile::Minecraft).
     /// If you need to customize this behaviour, use a [`Builder`].
     #[cfg_attr(doc_cfg, doc(cfg(feature = "rt-async-std")))]
     pub async fn connect<A: ToSocketAddrs>(address: A, password: &str) -> Result<Self> {
         Self::builder()
-            .enable_minecraft_quirks(true)
+            .profile(ServerProfile::Minecraft)
             .connect(address, password)
             .await
     }
@@ -25,20 +28,77 @@ impl Connection<AsyncStdStream> {
 impl Builder<AsyncStdStream> {
     /// Connect to an rcon server using the [async-std](async_std) runtime.
     #[cfg_attr(doc_cfg, doc(cfg(feature = "rt-async-std")))]
-    #[cfg_attr(not(feature = "tokio"), allow(unused_mut))]
     pub async fn connect<A: ToSocketAddrs>(
-        mut self,
+        self,
         address: A,
         password: &str,
     ) -> Result<Connection<AsyncStdStream>> {
+        let builder = self.with_async_std_sleep();
+        let io = builder.connect_io(TcpStream::connect(address)).await?;
+
+        builder.handshake(AsyncStdStream(io), password).await
+    }
+
+    /// Create a [`ReconnectingConnection`] to an rcon server using the
+    /// [async-std](async_std) runtime.
+    ///
+    /// No connection is established until the first command, or until
+    /// [`ReconnectingConnection::connect`] is called.
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "rt-async-std")))]
+    pub fn reconnecting<A: Into<String>>(
+        self,
+        address: A,
+        password: &str,
+    ) -> ReconnectingConnection<AsyncStdStream> {
+        let address = address.into();
+
+        self.with_async_std_sleep().reconnect_with(
+            move || {
+                let address = address.clone();
+                async move { TcpStream::connect(address).await.map(AsyncStdStream) }
+            },
+            password,
+        )
+    }
+
+    /// Create a [`Pool`] of connections to an rcon server using the
+    /// [async-std](async_std) runtime.
+    ///
+    /// No connection is established until one is checked out, or until
+    /// [`Pool::warm_up`] is called.
+    ///
+    /// # Panics
+    ///
+    /// If `config` is invalid, see [`pool_with`](Self::pool_with).
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "rt-async-std")))]
+    pub fn pool<A: Into<String>>(
+        self,
+        address: A,
+        password: &str,
+        config: PoolConfig,
+    ) -> Pool<AsyncStdStream> {
+        let address = address.into();
+
+        self.with_async_std_sleep().pool_with(
+            move || {
+                let address = address.clone();
+                async move { TcpStream::connect(address).await.map(AsyncStdStream) }
+            },
+            password,
+            config,
+        )
+    }
+
+    #[cfg_attr(not(feature = "rt-tokio"), allow(unused_mut))]
+    fn with_async_std_sleep(mut self) -> Self {
         // If the `rt-tokio` feature flag is also enabled the sleep_fn will use it by default, so
         // we have to change it to use async-std instead.
         #[cfg(feature = "rt-tokio")]
         if let crate::SleepFn::Tokio = self.sleep_fn {
             self.sleep_fn = crate::SleepFn::AsyncStd;
         }
-        self.handshake(AsyncStdStream(TcpStream::connect(address).await?), password)
-            .await
+
+        self
     }
 }
 
@@ -84,3 +144,23 @@ impl TokioWrite for AsyncStdStream {
         Pin::new(&mut self.0).poll_write_vectored(cx, bufs)
     }
 }
+
+impl Listener for async_std::net::TcpListener {
+    type Stream = AsyncStdStream;
+
+    fn accept(&self) -> Accept<'_, AsyncStdStream> {
+        Box::pin(async move {
+            let (io, address) = async_std::net::TcpListener::accept(self).await?;
+
+            Ok((AsyncStdStream(io), address))
+        })
+    }
+
+    fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+        async_std::task::spawn(future);
+    }
+
+    fn sleep(duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+}
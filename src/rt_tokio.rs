@@ -1,16 +1,24 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::{TcpStream, ToSocketAddrs};
 
-use crate::{Builder, Connection, Result};
+use crate::server::{Accept, Listener};
+use crate::{
+    Builder, Client, Connection, Pool, PoolConfig, ReconnectingConnection, Result, ServerProfile,
+};
 
 impl Connection<TcpStream> {
     /// Connect to an rcon server using the [Tokio](tokio) runtime.
     ///
-    /// By default this enables Minecraft quirks.
+    /// By default this uses the [Minecraft profile](crate::ServerProfile::Minecraft).
     /// If you need to customize this behaviour, use a [`Builder`].
     #[cfg_attr(doc_cfg, doc(cfg(feature = "rt-tokio")))]
     pub async fn connect<A: ToSocketAddrs>(address: A, password: &str) -> Result<Self> {
         Self::builder()
-            .enable_minecraft_quirks(true)
+            .profile(ServerProfile::Minecraft)
             .connect(address, password)
             .await
     }
@@ -24,7 +32,80 @@ impl Builder<TcpStream> {
         address: A,
         password: &str,
     ) -> Result<Connection<TcpStream>> {
-        self.handshake(TcpStream::connect(address).await?, password)
-            .await
+        let io = self.connect_io(TcpStream::connect(address)).await?;
+
+        self.handshake(io, password).await
+    }
+
+    /// Create a [`ReconnectingConnection`] to an rcon server using the [Tokio](tokio) runtime.
+    ///
+    /// No connection is established until the first command, or until
+    /// [`ReconnectingConnection::connect`] is called.
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "rt-tokio")))]
+    pub fn reconnecting<A: Into<String>>(
+        self,
+        address: A,
+        password: &str,
+    ) -> ReconnectingConnection<TcpStream> {
+        let address = address.into();
+
+        self.reconnect_with(move || TcpStream::connect(address.clone()), password)
+    }
+
+    /// Create a [`Pool`] of connections to an rcon server using the [Tokio](tokio) runtime.
+    ///
+    /// No connection is established until one is checked out, or until
+    /// [`Pool::warm_up`] is called.
+    ///
+    /// # Panics
+    ///
+    /// If `config` is invalid, see [`pool_with`](Self::pool_with).
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "rt-tokio")))]
+    pub fn pool<A: Into<String>>(
+        self,
+        address: A,
+        password: &str,
+        config: PoolConfig,
+    ) -> Pool<TcpStream> {
+        let address = address.into();
+
+        self.pool_with(
+            move || TcpStream::connect(address.clone()),
+            password,
+            config,
+        )
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + 'static> Connection<T> {
+    /// Turn the connection into a [`Client`], running its [driver](crate::Driver)
+    /// as a Tokio task.
+    ///
+    /// The task ends once every clone of the client has been dropped. Errors of the
+    /// connection are only visible as [`Error::Closed`](crate::Error::Closed) on
+    /// subsequent commands; use [`into_client`](Self::into_client) to observe them.
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "rt-tokio")))]
+    pub fn spawn_client(self) -> Client {
+        let (client, driver) = self.into_client();
+
+        tokio::spawn(driver.run());
+
+        client
+    }
+}
+
+impl Listener for tokio::net::TcpListener {
+    type Stream = TcpStream;
+
+    fn accept(&self) -> Accept<'_, TcpStream> {
+        Box::pin(tokio::net::TcpListener::accept(self))
+    }
+
+    fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+        tokio::spawn(future);
+    }
+
+    fn sleep(duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(tokio::time::sleep(duration))
     }
 }
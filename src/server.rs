@@ -0,0 +1,354 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! The server side of the protocol, for exposing an rcon endpoint from a Rust service.
+//!
+//! A [`Server`] authenticates clients with an [`Authenticator`] and passes their
+//! commands to an async handler. It is compatible with this crate's client and
+//! with the usual admin tools:
+//!
+//! - replies longer than 4096 bytes are split into several packets,
+//! - empty commands are answered with an empty packet, so clients can use them
+//!   as [end markers](crate::EndMarker),
+//! - `ResponseValue` packets are mirrored the way Source dedicated servers do,
+//!   see [`SourceMirror`](crate::SourceMirror).
+//!
+//! ```no_run
+//! # #[cfg(feature = "rt-tokio")]
+//! # async fn doc() -> std::io::Result<()> {
+//! use rcon::server::Server;
+//!
+//! let server = Server::new("hunter2".to_owned(), |cmd: String| async move {
+//!     format!("Unknown command: {}", cmd)
+//! });
+//! let listener = tokio::net::TcpListener::bind("0.0.0.0:25575").await?;
+//! server.serve(listener).await;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::{self, Debug, Display, Formatter};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::codec::PacketDecoder;
+use crate::packet::{Packet, PacketType};
+use crate::terminator::MIRROR_TRAILER;
+use crate::{Result, DEFAULT_MAX_PACKET_SIZE, READ_BUFFER_SIZE};
+
+/// The maximum body size of a reply packet, as used by Source dedicated servers.
+pub const MAX_REPLY_PACKET_BODY: usize = 4096;
+
+/// The number of failed authentication attempts after which a client is disconnected
+/// by default.
+pub const DEFAULT_MAX_AUTH_FAILURES: usize = 3;
+
+/// How long to wait before accepting connections again after an error that is not
+/// specific to a single connection, like running out of file descriptors.
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+type Handler = Arc<
+    dyn Fn(String) -> Pin<Box<dyn Future<Output = std::result::Result<String, String>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// The outcome of a command handler.
+///
+/// A handler either answers with a `String` or returns a `Result`, where an error
+/// is logged and disconnects the client instead of answering, so clients can tell
+/// failures from replies.
+pub trait IntoReply {
+    /// The reply, or the error to log before disconnecting the client.
+    fn into_reply(self) -> std::result::Result<String, String>;
+}
+
+impl IntoReply for String {
+    fn into_reply(self) -> std::result::Result<String, String> {
+        Ok(self)
+    }
+}
+
+impl<E: Display> IntoReply for std::result::Result<String, E> {
+    fn into_reply(self) -> std::result::Result<String, String> {
+        self.map_err(|err| err.to_string())
+    }
+}
+
+/// Decides whether a client may execute commands.
+pub trait Authenticator: Send + Sync {
+    /// Whether `password` is correct.
+    fn authenticate(&self, password: &[u8]) -> bool;
+}
+
+impl Authenticator for String {
+    fn authenticate(&self, password: &[u8]) -> bool {
+        self.as_bytes() == password
+    }
+}
+
+impl Authenticator for &'static str {
+    fn authenticate(&self, password: &[u8]) -> bool {
+        self.as_bytes() == password
+    }
+}
+
+impl<F: Fn(&[u8]) -> bool + Send + Sync> Authenticator for F {
+    fn authenticate(&self, password: &[u8]) -> bool {
+        self(password)
+    }
+}
+
+/// The future returned by [`Listener::accept`].
+pub type Accept<'a, S> = Pin<Box<dyn Future<Output = io::Result<(S, SocketAddr)>> + Send + 'a>>;
+
+/// A source of incoming connections for [`Server::serve`].
+///
+/// Implemented for the TCP listeners of Tokio and async-std when the respective
+/// runtime feature is enabled.
+pub trait Listener: Send {
+    /// The transport of an accepted connection.
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    /// Wait for the next connection.
+    fn accept(&self) -> Accept<'_, Self::Stream>;
+
+    /// Run the handling of a connection in the background.
+    fn spawn<F: Future<Output = ()> + Send + 'static>(future: F);
+
+    /// Wait for `duration` to elapse.
+    fn sleep(duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+/// An rcon server.
+///
+/// Cloning a server is cheap, which allows serving several listeners.
+#[derive(Clone)]
+pub struct Server {
+    authenticator: Arc<dyn Authenticator>,
+    handler: Handler,
+    max_packet_size: usize,
+    max_auth_failures: usize,
+}
+
+impl Debug for Server {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Server")
+            .field("max_packet_size", &self.max_packet_size)
+            .field("max_auth_failures", &self.max_auth_failures)
+            .finish()
+    }
+}
+
+impl Server {
+    /// Create a server that executes the commands of clients authenticated by
+    /// `authenticator` with `handler`.
+    ///
+    /// Commands are decoded as UTF-8, replacing invalid sequences with `U+FFFD`. A
+    /// handler returning an error disconnects the client, see [`IntoReply`].
+    pub fn new<A, F, Fut>(authenticator: A, handler: F) -> Self
+    where
+        A: Authenticator + 'static,
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future + Send + 'static,
+        Fut::Output: IntoReply,
+    {
+        Self {
+            authenticator: Arc::new(authenticator),
+            handler: Arc::new(move |cmd| {
+                let reply = handler(cmd);
+
+                Box::pin(async move { reply.await.into_reply() })
+            }),
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            max_auth_failures: DEFAULT_MAX_AUTH_FAILURES,
+        }
+    }
+
+    /// Set the maximum size of a packet accepted from clients.
+    ///
+    /// See [`Builder::max_packet_size`](crate::Builder::max_packet_size).
+    pub fn max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
+
+    /// Set the number of failed authentication attempts after which a client is
+    /// disconnected, [`DEFAULT_MAX_AUTH_FAILURES`] by default. Zero allows unlimited
+    /// attempts.
+    ///
+    /// This slows down guessing the password, as every few guesses need a new
+    /// connection.
+    pub fn max_auth_failures(mut self, max_auth_failures: usize) -> Self {
+        self.max_auth_failures = max_auth_failures;
+        self
+    }
+
+    /// Accept connections from `listener` and serve each of them in the background.
+    ///
+    /// Never returns. Errors accepting a connection are logged, and accepting pauses
+    /// briefly after errors not specific to the connection, like running out of file
+    /// descriptors.
+    pub async fn serve<L: Listener>(self, listener: L) {
+        loop {
+            let (io, address) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(err) if is_connection_error(&err) => {
+                    log::debug!("failed to accept a connection: {}", err);
+                    continue;
+                }
+                Err(err) => {
+                    log::error!("failed to accept a connection: {}", err);
+                    L::sleep(ACCEPT_ERROR_DELAY).await;
+                    continue;
+                }
+            };
+            let server = self.clone();
+
+            // a misbehaving client only affects its own connection
+            L::spawn(async move {
+                let _ = server.serve_client(io, Some(address)).await;
+            });
+        }
+    }
+
+    /// Serve a single connection until the client disconnects.
+    ///
+    /// Clients sending commands before authenticating or failing to authenticate
+    /// [too often](Self::max_auth_failures) are disconnected, as are clients whose
+    /// command failed in the handler.
+    pub async fn serve_connection<T: AsyncRead + AsyncWrite + Unpin>(&self, io: T) -> Result<()> {
+        self.serve_client(io, None).await
+    }
+
+    /// Serve a connection from the client at `address`, if known.
+    async fn serve_client<T: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        mut io: T,
+        address: Option<SocketAddr>,
+    ) -> Result<()> {
+        let mut decoder = PacketDecoder::new().max_packet_size(self.max_packet_size);
+        let mut buf = [0u8; READ_BUFFER_SIZE];
+        let mut authenticated = false;
+        let mut auth_failures = 0;
+
+        loop {
+            let packet = match decoder.decode()? {
+                Some(packet) => packet,
+                None => {
+                    let read = io.read(&mut buf).await?;
+
+                    if read == 0 {
+                        return Ok(());
+                    }
+
+                    decoder.feed(&buf[..read]);
+                    continue;
+                }
+            };
+            let id = packet.get_id();
+            let mut replies = Vec::new();
+
+            match packet.get_type() {
+                PacketType::Auth => {
+                    authenticated = self.authenticator.authenticate(packet.get_body());
+
+                    // failed authentication is signaled with an id of -1
+                    let id = if authenticated { id } else { -1 };
+                    replies.push(Packet::new(id, PacketType::AuthResponse, Vec::new()));
+
+                    if !authenticated {
+                        auth_failures += 1;
+                    }
+                }
+                _ if !authenticated => return Ok(()),
+                PacketType::ResponseValue => {
+                    replies.push(Packet::new(id, PacketType::ResponseValue, Vec::new()));
+                    replies.push(Packet::new(
+                        id,
+                        PacketType::ResponseValue,
+                        MIRROR_TRAILER.to_vec(),
+                    ));
+                }
+                // the decoder cannot tell `ExecCommand` from `AuthResponse`, which share a value
+                PacketType::ExecCommand | PacketType::AuthResponse => {
+                    // end markers are answered without bothering the handler
+                    let reply = match packet.get_body() {
+                        b"" => String::new(),
+                        cmd => {
+                            let cmd = String::from_utf8_lossy(cmd).into_owned();
+
+                            match (self.handler)(cmd).await {
+                                Ok(reply) => reply,
+                                Err(err) => {
+                                    match address {
+                                        Some(address) => log::warn!(
+                                            "command from {} failed, disconnecting: {}",
+                                            address,
+                                            err
+                                        ),
+                                        None => log::warn!(
+                                            "command failed, disconnecting the client: {}",
+                                            err
+                                        ),
+                                    }
+                                    return Ok(());
+                                }
+                            }
+                        }
+                    };
+
+                    replies.extend(split_reply(id, reply.as_bytes()));
+                }
+                PacketType::Unknown(_) => {}
+            }
+
+            let mut out = Vec::new();
+
+            for reply in replies {
+                reply.encode(&mut out);
+            }
+
+            io.write_all(&out).await?;
+
+            if self.max_auth_failures > 0 && auth_failures >= self.max_auth_failures {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Whether accepting failed because of the connection rather than the listener.
+fn is_connection_error(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::Interrupted
+    )
+}
+
+/// Split a reply into packets of at most [`MAX_REPLY_PACKET_BODY`] bytes.
+pub(crate) fn split_reply(id: i32, reply: &[u8]) -> Vec<Packet> {
+    if reply.is_empty() {
+        return vec![Packet::new(id, PacketType::ResponseValue, Vec::new())];
+    }
+
+    reply
+        .chunks(MAX_REPLY_PACKET_BODY)
+        .map(|chunk| Packet::new(id, PacketType::ResponseValue, chunk.to_vec()))
+        .collect()
+}
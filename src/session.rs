@@ -0,0 +1,345 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::codec::PacketDecoder;
+use crate::packet::{Packet, PacketType};
+use crate::terminator::{EndMarker, PacketAction, ResponseState, ResponseTerminator};
+use crate::Result;
+
+const INITIAL_PACKET_ID: i32 = 1;
+/// How many ids of completed or cancelled commands are remembered to recognize
+/// their late packets.
+const MAX_FINISHED_IDS: usize = 64;
+
+/// Something that happened on an rcon session.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The server accepted the password.
+    Authenticated,
+    /// The server rejected the password.
+    AuthFailed,
+    /// The complete response to the command with the given id has been received.
+    ///
+    /// The body is the concatenation of the bodies of all response packets. It is
+    /// left undecoded, as servers split long responses at arbitrary byte offsets.
+    Response { id: i32, body: Vec<u8> },
+    /// A packet was received that does not belong to any pending request.
+    ///
+    /// The packet has been discarded. Packets of commands that have already completed
+    /// or were [cancelled](ClientSession::cancel) are discarded silently instead.
+    UnexpectedPacket { id: i32, ptype: PacketType },
+}
+
+#[derive(Debug)]
+struct PendingResponse {
+    state: ResponseState,
+    terminator: Arc<dyn ResponseTerminator>,
+}
+
+/// An IO-free rcon client state machine.
+///
+/// The session encodes outgoing packets, decodes incoming bytes, correlates
+/// response packets with the commands that caused them and detects the end of
+/// multi-packet responses. It never performs IO itself: the caller writes the
+/// packets returned by [`poll_transmit`](Self::poll_transmit) to the transport,
+/// passes everything read from the transport to [`receive`](Self::receive) and
+/// then collects the resulting [`Event`]s with [`poll_event`](Self::poll_event).
+///
+/// # Example
+///
+/// ```
+/// use rcon::{ClientSession, Event};
+///
+/// let mut session = ClientSession::new();
+/// session.auth("hunter2");
+/// let auth_packet = session.poll_transmit().unwrap();
+/// # let _ = auth_packet;
+///
+/// // an AuthResponse with the id of the auth packet
+/// session.receive(&[10, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 0, 0]).unwrap();
+/// assert_eq!(session.poll_event(), Some(Event::Authenticated));
+/// ```
+#[derive(Debug)]
+pub struct ClientSession {
+    decoder: PacketDecoder,
+    transmits: VecDeque<Vec<u8>>,
+    events: VecDeque<Event>,
+    next_packet_id: i32,
+    auth_id: Option<i32>,
+    auth_preamble_received: bool,
+    pending: VecDeque<PendingResponse>,
+    finished: VecDeque<i32>,
+    terminator: Arc<dyn ResponseTerminator>,
+    last_response_packets: usize,
+}
+
+impl Default for ClientSession {
+    fn default() -> Self {
+        Self {
+            decoder: PacketDecoder::new(),
+            transmits: VecDeque::new(),
+            events: VecDeque::new(),
+            next_packet_id: INITIAL_PACKET_ID,
+            auth_id: None,
+            auth_preamble_received: false,
+            pending: VecDeque::new(),
+            finished: VecDeque::new(),
+            terminator: Arc::new(EndMarker::default()),
+            last_response_packets: 0,
+        }
+    }
+}
+
+impl ClientSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how the end of multi-packet responses is detected.
+    ///
+    /// Defaults to an [`EndMarker`] with an empty command.
+    pub fn response_terminator(mut self, terminator: Arc<dyn ResponseTerminator>) -> Self {
+        self.terminator = terminator;
+        self
+    }
+
+    /// Change how the end of multi-packet responses is detected for subsequent commands.
+    pub fn set_response_terminator(&mut self, terminator: Arc<dyn ResponseTerminator>) {
+        self.terminator = terminator;
+    }
+
+    /// Set the maximum value accepted in the length field of received packets.
+    ///
+    /// See [`PacketDecoder::max_packet_size`].
+    pub fn max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.decoder = self.decoder.max_packet_size(max_packet_size);
+        self
+    }
+
+    /// Queue an authentication request and return its packet id.
+    pub fn auth<P: AsRef<[u8]>>(&mut self, password: P) -> i32 {
+        let id = self.queue(PacketType::Auth, password.as_ref());
+        self.auth_id = Some(id);
+        id
+    }
+
+    /// Queue a command and return its packet id.
+    ///
+    /// The id is reported back in [`Event::Response`] once the full response has arrived.
+    pub fn command<C: AsRef<[u8]>>(&mut self, cmd: C) -> i32 {
+        self.command_with_terminator(cmd, self.terminator.clone())
+    }
+
+    /// Queue a command whose response is terminated differently than the session's default.
+    pub fn command_with_terminator<C: AsRef<[u8]>>(
+        &mut self,
+        cmd: C,
+        terminator: Arc<dyn ResponseTerminator>,
+    ) -> i32 {
+        let command_id = self.queue(PacketType::ExecCommand, cmd.as_ref());
+        let end_id = terminator
+            .end_marker()
+            .map(|(ptype, body)| self.queue(ptype, body));
+
+        self.pending.push_back(PendingResponse {
+            state: ResponseState {
+                command_id,
+                end_id,
+                body: Vec::new(),
+                packets: 0,
+                end_packets: 0,
+            },
+            terminator,
+        });
+
+        command_id
+    }
+
+    /// Stop waiting for the response to the command with the given id.
+    ///
+    /// No [`Event::Response`] is emitted for the command, and packets answering it
+    /// later on are discarded.
+    pub fn cancel(&mut self, id: i32) {
+        let index = self
+            .pending
+            .iter()
+            .position(|pending| pending.state.command_id == id);
+
+        if let Some(index) = index {
+            self.finish(index);
+        }
+    }
+
+    /// Whether the server sent a packet before answering the authentication request,
+    /// as Source dedicated servers do.
+    pub fn auth_preamble_received(&self) -> bool {
+        self.auth_preamble_received
+    }
+
+    /// Take the next encoded packet that should be written to the transport.
+    pub fn poll_transmit(&mut self) -> Option<Vec<u8>> {
+        self.transmits.pop_front()
+    }
+
+    /// Feed bytes received from the transport into the session.
+    pub fn receive(&mut self, data: &[u8]) -> Result<()> {
+        self.decoder.feed(data);
+
+        while let Some(packet) = self.decoder.decode()? {
+            self.handle_packet(packet);
+        }
+
+        Ok(())
+    }
+
+    /// Process a packet that has already been decoded, e.g. by a custom framing layer.
+    ///
+    /// Response packets are matched to the pending command with the same id.
+    pub fn handle_packet(&mut self, packet: Packet) {
+        let id = packet.get_id();
+        let ptype = packet.get_type();
+
+        if let Some(auth_id) = self.auth_id {
+            match ptype {
+                // the id of a failed auth response is -1
+                PacketType::AuthResponse => {
+                    self.auth_id = None;
+                    self.events.push_back(if packet.is_error() {
+                        Event::AuthFailed
+                    } else {
+                        Event::Authenticated
+                    });
+                }
+                // Source dedicated servers send an empty response value before the auth response
+                PacketType::ResponseValue if id == auth_id && packet.get_body().is_empty() => {
+                    self.auth_preamble_received = true;
+                }
+                _ => self.events.push_back(Event::UnexpectedPacket { id, ptype }),
+            }
+
+            return;
+        }
+
+        let index = match self.position(id) {
+            Some(index) if ptype == PacketType::ResponseValue => index,
+            _ => {
+                // e.g. the rest of a response truncated by `SinglePacket`
+                if !self.finished.contains(&id) {
+                    self.events.push_back(Event::UnexpectedPacket { id, ptype });
+                }
+
+                return;
+            }
+        };
+        let pending = &mut self.pending[index];
+
+        let action = pending.terminator.on_packet(&pending.state, &packet);
+        let state = &mut pending.state;
+
+        state.packets += 1;
+
+        if state.end_id == Some(id) {
+            state.end_packets += 1;
+        }
+
+        match action {
+            PacketAction::Append => state.body.extend_from_slice(packet.get_body()),
+            PacketAction::AppendAndComplete => {
+                state.body.extend_from_slice(packet.get_body());
+                self.complete(index);
+            }
+            PacketAction::Ignore => {}
+            PacketAction::Complete => self.complete(index),
+        }
+    }
+
+    /// How long the caller should wait for further packets before calling
+    /// [`handle_idle`](Self::handle_idle), if the response terminator uses an idle timeout
+    /// and a response is pending.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.pending.front()?.terminator.idle_timeout()
+    }
+
+    /// Notify the session that no packet has been received for the [idle
+    /// timeout](Self::idle_timeout), completing the oldest pending response.
+    pub fn handle_idle(&mut self) {
+        if self.idle_timeout().is_some() {
+            self.complete(0);
+        }
+    }
+
+    /// The number of packets the most recently completed response consisted of,
+    /// including packets marking its end.
+    pub fn last_response_packets(&self) -> usize {
+        self.last_response_packets
+    }
+
+    /// Take the next event produced by received packets.
+    pub fn poll_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    /// The index of the pending response that a packet with the given id belongs to.
+    fn position(&self, id: i32) -> Option<usize> {
+        self.pending
+            .iter()
+            .position(|pending| pending.state.command_id == id || pending.state.end_id == Some(id))
+    }
+
+    fn complete(&mut self, index: usize) {
+        if let Some(state) = self.finish(index) {
+            self.last_response_packets = state.packets;
+            self.events.push_back(Event::Response {
+                id: state.command_id,
+                body: state.body,
+            });
+        }
+    }
+
+    /// Stop waiting for a response, remembering its ids to recognize late packets.
+    fn finish(&mut self, index: usize) -> Option<ResponseState> {
+        let state = self.pending.remove(index)?.state;
+
+        for id in std::iter::once(state.command_id).chain(state.end_id) {
+            if self.finished.len() == MAX_FINISHED_IDS {
+                self.finished.pop_front();
+            }
+            self.finished.push_back(id);
+        }
+
+        Some(state)
+    }
+
+    fn queue(&mut self, ptype: PacketType, body: &[u8]) -> i32 {
+        let id = self.generate_packet_id();
+        let mut buf = Vec::new();
+
+        Packet::new(id, ptype, body.into()).encode(&mut buf);
+        self.transmits.push_back(buf);
+
+        id
+    }
+
+    fn generate_packet_id(&mut self) -> i32 {
+        let id = self.next_packet_id;
+
+        // only use positive ids as the server uses negative ids to signal
+        // a failed authentication request
+        self.next_packet_id = self
+            .next_packet_id
+            .checked_add(1)
+            .unwrap_or(INITIAL_PACKET_ID);
+
+        id
+    }
+}
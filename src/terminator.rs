@@ -0,0 +1,240 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+use crate::packet::{Packet, PacketType};
+
+/// Decides when the response to a command is complete.
+///
+/// The rcon protocol has no explicit end-of-response marker, and servers split long
+/// responses into several packets. Implementations of this trait detect the end of a
+/// response, usually by sending an extra [`end_marker`](Self::end_marker) packet after
+/// the command and waiting for the server to answer it.
+///
+/// The built-in implementations are [`EndMarker`], [`SourceMirror`], [`SinglePacket`],
+/// [`IdleTimeout`] and [`KnownSuffix`].
+pub trait ResponseTerminator: Debug + Send + Sync {
+    /// The type and body of a packet to send after each command, if any.
+    ///
+    /// Its id is available as [`ResponseState::end_id`].
+    fn end_marker(&self) -> Option<(PacketType, &[u8])> {
+        None
+    }
+
+    /// Decide what to do with a packet received while waiting for a response.
+    fn on_packet(&self, state: &ResponseState, packet: &Packet) -> PacketAction;
+
+    /// Consider the response complete once no packet has been received for this long.
+    ///
+    /// The session cannot measure time itself: whoever drives it calls
+    /// [`ClientSession::handle_idle`](crate::ClientSession::handle_idle) when the timeout
+    /// expires.
+    fn idle_timeout(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// What to do with a packet received while waiting for a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketAction {
+    /// Append the body to the response and keep waiting.
+    Append,
+    /// Append the body to the response, which is now complete.
+    AppendAndComplete,
+    /// Drop the packet and keep waiting.
+    Ignore,
+    /// Drop the packet. The response is complete.
+    Complete,
+}
+
+/// The progress of a response, as seen by a [`ResponseTerminator`].
+#[derive(Debug)]
+pub struct ResponseState {
+    pub(crate) command_id: i32,
+    pub(crate) end_id: Option<i32>,
+    pub(crate) body: Vec<u8>,
+    pub(crate) packets: usize,
+    pub(crate) end_packets: usize,
+}
+
+impl ResponseState {
+    /// The id of the command packet.
+    pub fn command_id(&self) -> i32 {
+        self.command_id
+    }
+
+    /// The id of the end marker packet, if one was sent.
+    pub fn end_id(&self) -> Option<i32> {
+        self.end_id
+    }
+
+    /// The response body received so far.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// The number of packets received so far, not counting the one being inspected.
+    pub fn packets(&self) -> usize {
+        self.packets
+    }
+
+    /// The number of packets with the end marker's id received so far,
+    /// not counting the one being inspected.
+    pub fn end_packets(&self) -> usize {
+        self.end_packets
+    }
+
+    fn is_end_packet(&self, packet: &Packet) -> bool {
+        self.end_id == Some(packet.get_id())
+    }
+}
+
+/// Send a command after each command; the response is complete once it has been answered.
+///
+/// The server processes packets in order, so any packet answering the end marker
+/// means that the response to the previous command is complete. The end marker is
+/// an empty command by default. Servers that do not answer empty commands, such as
+/// Factorio, need a command without side effects instead.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EndMarker {
+    cmd: Vec<u8>,
+}
+
+impl EndMarker {
+    /// Use `cmd` as the end marker command. Its response is discarded.
+    pub fn new<C: Into<Vec<u8>>>(cmd: C) -> Self {
+        Self { cmd: cmd.into() }
+    }
+}
+
+impl ResponseTerminator for EndMarker {
+    fn end_marker(&self) -> Option<(PacketType, &[u8])> {
+        Some((PacketType::ExecCommand, &self.cmd))
+    }
+
+    fn on_packet(&self, state: &ResponseState, packet: &Packet) -> PacketAction {
+        if state.is_end_packet(packet) {
+            PacketAction::Complete
+        } else {
+            PacketAction::Append
+        }
+    }
+}
+
+/// The body of the packet following a mirrored `ResponseValue` packet.
+pub(crate) const MIRROR_TRAILER: &[u8] = &[0, 0, 0, 1, 0, 0, 0, 0];
+
+/// Send an empty `ResponseValue` packet after each command.
+///
+/// Source dedicated servers mirror this packet back, followed by another packet
+/// with the same id and the body `00 00 00 01 00 00 00 00`. The response is
+/// complete once both have been received. See the
+/// [Valve developer wiki](https://developer.valvesoftware.com/wiki/Source_RCON_Protocol#Multiple-packet_Responses).
+///
+/// The trailer is compared without its trailing null bytes, as implementations
+/// disagree on whether the packet terminator is part of it. Other packets with the
+/// id of the end marker are ignored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceMirror;
+
+impl ResponseTerminator for SourceMirror {
+    fn end_marker(&self) -> Option<(PacketType, &[u8])> {
+        Some((PacketType::ResponseValue, b""))
+    }
+
+    fn on_packet(&self, state: &ResponseState, packet: &Packet) -> PacketAction {
+        match (state.is_end_packet(packet), state.end_packets()) {
+            // the mirrored packet
+            (true, 0) => PacketAction::Ignore,
+            (true, _) if is_mirror_trailer(packet.get_body()) => PacketAction::Complete,
+            (true, _) => PacketAction::Ignore,
+            (false, _) => PacketAction::Append,
+        }
+    }
+}
+
+fn is_mirror_trailer(body: &[u8]) -> bool {
+    fn trim(body: &[u8]) -> &[u8] {
+        let len = body
+            .iter()
+            .rposition(|&byte| byte != 0)
+            .map_or(0, |i| i + 1);
+        &body[..len]
+    }
+
+    trim(body) == trim(MIRROR_TRAILER)
+}
+
+/// Treat the first packet received after a command as its complete response.
+///
+/// Long responses are truncated with this terminator, and their remaining packets
+/// are discarded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SinglePacket;
+
+impl ResponseTerminator for SinglePacket {
+    fn on_packet(&self, _state: &ResponseState, _packet: &Packet) -> PacketAction {
+        PacketAction::AppendAndComplete
+    }
+}
+
+/// Consider the response complete once the server has been silent for a while.
+///
+/// This works with any server, but adds the timeout to the duration of every command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdleTimeout(pub Duration);
+
+impl ResponseTerminator for IdleTimeout {
+    fn on_packet(&self, _state: &ResponseState, _packet: &Packet) -> PacketAction {
+        PacketAction::Append
+    }
+
+    fn idle_timeout(&self) -> Option<Duration> {
+        Some(self.0)
+    }
+}
+
+/// Consider the response complete once it ends with a known suffix.
+///
+/// Useful for servers that terminate their output with a prompt or a fixed line.
+/// The suffix is kept in the response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnownSuffix {
+    suffix: Vec<u8>,
+}
+
+impl KnownSuffix {
+    pub fn new<S: Into<Vec<u8>>>(suffix: S) -> Self {
+        Self {
+            suffix: suffix.into(),
+        }
+    }
+}
+
+impl ResponseTerminator for KnownSuffix {
+    fn on_packet(&self, state: &ResponseState, packet: &Packet) -> PacketAction {
+        let body = packet.get_body();
+
+        // the suffix may be split across packets
+        let complete = if body.len() >= self.suffix.len() {
+            body.ends_with(&self.suffix)
+        } else {
+            let (head, tail) = self.suffix.split_at(self.suffix.len() - body.len());
+            body == tail && state.body().ends_with(head)
+        };
+
+        if complete {
+            PacketAction::AppendAndComplete
+        } else {
+            PacketAction::Append
+        }
+    }
+}
@@ -0,0 +1,557 @@
+// Copyright (c) 2015 [rust-rcon developers]
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! A scriptable in-process rcon server for testing code that uses this crate.
+//!
+//! The [`MockServer`] answers commands as scripted by the test, and emulates the
+//! quirks of the supported games, so tests can run without a real game server.
+//! Wrapping a transport in [`FaultyIo`] tests how a connection copes with a bad network.
+//!
+//! ```
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> rcon::Result<()> {
+//! use rcon::testing::{Flavor, MockServer, Reply};
+//! use rcon::{Connection, ServerProfile};
+//!
+//! let (io, handle) = MockServer::new("hunter2")
+//!     .flavor(Flavor::Factorio)
+//!     .expect("/players", Reply::text("Players (0):\n"))
+//!     .spawn();
+//!
+//! let mut conn = <Connection<_>>::builder()
+//!     .profile(ServerProfile::Factorio)
+//!     .handshake(io, "hunter2")
+//!     .await?;
+//! assert_eq!(conn.cmd("/players").await?, "Players (0):\n");
+//!
+//! handle.assert_done();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf};
+use tokio::time::Sleep;
+
+use crate::codec::PacketDecoder;
+use crate::packet::{Packet, PacketType};
+use crate::reconnect::XorShift;
+use crate::server::split_reply;
+use crate::terminator::MIRROR_TRAILER;
+use crate::READ_BUFFER_SIZE;
+
+/// The reply of Minecraft to an empty command.
+const MINECRAFT_EMPTY_COMMAND_REPLY: &str =
+    "Unknown or incomplete command, see below for error<--[HERE]";
+/// The reply of Factorio to `/version`, which clients use as an end marker.
+const FACTORIO_VERSION: &str = "1.1.110";
+
+/// The game whose rcon implementation a [`MockServer`] emulates.
+///
+/// All flavors split replies longer than 4096 bytes into several packets.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    /// Answers empty commands with an empty packet.
+    #[default]
+    Generic,
+    /// Answers empty commands with an "Unknown or incomplete command" error.
+    Minecraft,
+    /// Never answers empty commands, and answers `/version` with a version number.
+    Factorio,
+    /// Sends an empty `ResponseValue` packet before the `AuthResponse`, and mirrors
+    /// `ResponseValue` packets as described for [`SourceMirror`](crate::SourceMirror).
+    Source,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ReplyKind {
+    Body(Vec<u8>),
+    Drop,
+    Disconnect,
+}
+
+/// How a [`MockServer`] answers a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    kind: ReplyKind,
+    delay: Option<Duration>,
+}
+
+impl Reply {
+    /// Answer with `text`.
+    pub fn text<S: Into<String>>(text: S) -> Self {
+        Self::bytes(text.into().into_bytes())
+    }
+
+    /// Answer with raw bytes, e.g. to test other charsets.
+    pub fn bytes<B: Into<Vec<u8>>>(bytes: B) -> Self {
+        Self {
+            kind: ReplyKind::Body(bytes.into()),
+            delay: None,
+        }
+    }
+
+    /// Never answer, like a frozen server.
+    pub fn drop() -> Self {
+        Self {
+            kind: ReplyKind::Drop,
+            delay: None,
+        }
+    }
+
+    /// Close the connection instead of answering, like a crashing server.
+    pub fn disconnect() -> Self {
+        Self {
+            kind: ReplyKind::Disconnect,
+            delay: None,
+        }
+    }
+
+    /// Wait for `delay` before answering. Later commands wait as well, as the server
+    /// processes commands in order.
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    expected: VecDeque<(String, Reply)>,
+    responders: HashMap<String, Reply>,
+    received: Vec<String>,
+    unexpected: Vec<String>,
+}
+
+/// A scripted rcon server.
+///
+/// Commands are answered by the next [expectation](Self::expect) if it matches, then
+/// by the [responders](Self::respond), and otherwise with an empty reply. Commands
+/// matching neither are recorded as unexpected and fail [`MockHandle::assert_done`].
+#[derive(Debug)]
+pub struct MockServer {
+    password: String,
+    flavor: Flavor,
+    state: State,
+}
+
+impl MockServer {
+    /// Create a server that accepts `password`.
+    pub fn new<P: Into<String>>(password: P) -> Self {
+        Self {
+            password: password.into(),
+            flavor: Flavor::default(),
+            state: State::default(),
+        }
+    }
+
+    /// Emulate the quirks of a game. Defaults to [`Flavor::Generic`].
+    pub fn flavor(mut self, flavor: Flavor) -> Self {
+        self.flavor = flavor;
+        self
+    }
+
+    /// Expect `cmd` as the next command of the script and answer it with `reply`.
+    pub fn expect<C: Into<String>>(mut self, cmd: C, reply: Reply) -> Self {
+        self.state.expected.push_back((cmd.into(), reply));
+        self
+    }
+
+    /// Answer `cmd` with `reply` whenever it is received, in any order.
+    pub fn respond<C: Into<String>>(mut self, cmd: C, reply: Reply) -> Self {
+        self.state.responders.insert(cmd.into(), reply);
+        self
+    }
+
+    /// Serve a single in-memory connection in a Tokio task and return the client
+    /// end of it.
+    pub fn spawn(self) -> (DuplexStream, MockHandle) {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (mock, handle) = self.into_shared();
+
+        tokio::spawn(async move { mock.serve(server).await });
+
+        (client, handle)
+    }
+
+    /// Serve connections on a local TCP port in a Tokio task and return its address.
+    ///
+    /// All connections share the script.
+    #[cfg(feature = "rt-tokio")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "rt-tokio")))]
+    pub async fn listen(self) -> std::io::Result<(std::net::SocketAddr, MockHandle)> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let (mock, handle) = self.into_shared();
+
+        tokio::spawn(async move {
+            while let Ok((io, _)) = listener.accept().await {
+                let mock = mock.clone();
+                tokio::spawn(async move { mock.serve(io).await });
+            }
+        });
+
+        Ok((address, handle))
+    }
+
+    fn into_shared(self) -> (SharedMock, MockHandle) {
+        let state = Arc::new(Mutex::new(self.state));
+        let mock = SharedMock {
+            password: Arc::new(self.password),
+            flavor: self.flavor,
+            state: state.clone(),
+        };
+
+        (mock, MockHandle { state })
+    }
+}
+
+/// Inspects what a running [`MockServer`] has received.
+#[derive(Debug, Clone)]
+pub struct MockHandle {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockHandle {
+    /// The commands received so far, without the empty commands and `/version` commands
+    /// answered by the [flavor](Flavor).
+    pub fn received(&self) -> Vec<String> {
+        self.state.lock().unwrap().received.clone()
+    }
+
+    /// Panic if an expected command has not been received, or if an unexpected
+    /// one has.
+    pub fn assert_done(&self) {
+        let state = self.state.lock().unwrap();
+        let missing: Vec<_> = state.expected.iter().map(|(cmd, _)| cmd).collect();
+
+        assert!(
+            missing.is_empty(),
+            "expected commands not received: {:?}",
+            missing
+        );
+        assert!(
+            state.unexpected.is_empty(),
+            "unexpected commands received: {:?}",
+            state.unexpected
+        );
+    }
+}
+
+#[derive(Clone)]
+struct SharedMock {
+    password: Arc<String>,
+    flavor: Flavor,
+    state: Arc<Mutex<State>>,
+}
+
+impl SharedMock {
+    async fn serve<T: AsyncRead + AsyncWrite + Unpin>(&self, mut io: T) {
+        let mut decoder = PacketDecoder::new();
+        let mut buf = [0u8; READ_BUFFER_SIZE];
+
+        loop {
+            let packet = match decoder.decode() {
+                Ok(Some(packet)) => packet,
+                Ok(None) => match io.read(&mut buf).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(read) => {
+                        decoder.feed(&buf[..read]);
+                        continue;
+                    }
+                },
+                Err(_) => return,
+            };
+            let id = packet.get_id();
+            let mut out = Vec::new();
+
+            match packet.get_type() {
+                PacketType::Auth => {
+                    if self.flavor == Flavor::Source {
+                        Packet::new(id, PacketType::ResponseValue, Vec::new()).encode(&mut out);
+                    }
+
+                    let id = if packet.get_body() == self.password.as_bytes() {
+                        id
+                    } else {
+                        -1
+                    };
+                    Packet::new(id, PacketType::AuthResponse, Vec::new()).encode(&mut out);
+                }
+                PacketType::ResponseValue if self.flavor == Flavor::Source => {
+                    Packet::new(id, PacketType::ResponseValue, Vec::new()).encode(&mut out);
+                    Packet::new(id, PacketType::ResponseValue, MIRROR_TRAILER.to_vec())
+                        .encode(&mut out);
+                }
+                PacketType::ExecCommand | PacketType::AuthResponse => {
+                    let reply = match self.reply(packet.get_body()) {
+                        Some(reply) => reply,
+                        None => continue,
+                    };
+
+                    if let Some(delay) = reply.delay {
+                        tokio::time::sleep(delay).await;
+                    }
+
+                    match reply.kind {
+                        ReplyKind::Body(body) => {
+                            for packet in split_reply(id, &body) {
+                                packet.encode(&mut out);
+                            }
+                        }
+                        ReplyKind::Drop => continue,
+                        ReplyKind::Disconnect => return,
+                    }
+                }
+                _ => continue,
+            }
+
+            if io.write_all(&out).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// The reply to a command, or `None` if the flavor ignores it.
+    fn reply(&self, cmd: &[u8]) -> Option<Reply> {
+        if cmd.is_empty() {
+            return match self.flavor {
+                Flavor::Generic | Flavor::Source => Some(Reply::text("")),
+                Flavor::Minecraft => Some(Reply::text(MINECRAFT_EMPTY_COMMAND_REPLY)),
+                Flavor::Factorio => None,
+            };
+        }
+
+        if self.flavor == Flavor::Factorio && cmd == b"/version" {
+            return Some(Reply::text(FACTORIO_VERSION));
+        }
+
+        let cmd = String::from_utf8_lossy(cmd).into_owned();
+        let mut state = self.state.lock().unwrap();
+
+        state.received.push(cmd.clone());
+
+        if matches!(state.expected.front(), Some((expected, _)) if *expected == cmd) {
+            return state.expected.pop_front().map(|(_, reply)| reply);
+        }
+
+        if let Some(reply) = state.responders.get(&cmd) {
+            return Some(reply.clone());
+        }
+
+        state.unexpected.push(cmd);
+        Some(Reply::text(""))
+    }
+}
+
+/// A transport wrapper that injects faults into the IO of the wrapped transport.
+///
+/// All faults are driven by a seed, so a failing test can be reproduced by running it
+/// again with the same seed. Faults are opt-in and configured with builder-style methods.
+///
+/// Delays use Tokio's timer, which is best combined with a paused clock in tests,
+/// e.g. `#[tokio::test(start_paused = true)]`.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> rcon::Result<()> {
+/// use rcon::testing::{FaultyIo, MockServer, Reply};
+/// use rcon::Connection;
+///
+/// for seed in 0..10 {
+///     let (io, _) = MockServer::new("hunter2")
+///         .respond("list", Reply::text("There are 0 players"))
+///         .spawn();
+///     let io = FaultyIo::new(io, seed).split_reads();
+///
+///     let mut conn = <Connection<_>>::builder().handshake(io, "hunter2").await?;
+///     assert_eq!(conn.cmd("list").await?, "There are 0 players");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FaultyIo<T> {
+    io: T,
+    rng: XorShift,
+    split_reads: bool,
+    latency: Option<Duration>,
+    corrupt: f64,
+    truncate_after: Option<usize>,
+    reset_after: Option<usize>,
+    stall_writes: Option<Duration>,
+    read: usize,
+    read_delay: Option<Pin<Box<Sleep>>>,
+    write_delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl<T> FaultyIo<T> {
+    /// Wrap `io`, injecting faults derived from `seed`.
+    ///
+    /// Without further configuration no faults are injected.
+    pub fn new(io: T, seed: u64) -> Self {
+        Self {
+            io,
+            rng: XorShift::new(seed),
+            split_reads: false,
+            latency: None,
+            corrupt: 0.0,
+            truncate_after: None,
+            reset_after: None,
+            stall_writes: None,
+            read: 0,
+            read_delay: None,
+            write_delay: None,
+        }
+    }
+
+    /// Return fewer bytes than requested from reads, splitting packets at arbitrary
+    /// byte boundaries.
+    pub fn split_reads(mut self) -> Self {
+        self.split_reads = true;
+        self
+    }
+
+    /// Delay every read by a random duration of up to `max`.
+    pub fn latency(mut self, max: Duration) -> Self {
+        self.latency = Some(max);
+        self
+    }
+
+    /// Flip a random bit in each byte read with the given probability, between `0.0`
+    /// and `1.0`.
+    pub fn corrupt(mut self, probability: f64) -> Self {
+        self.corrupt = probability;
+        self
+    }
+
+    /// Signal the end of the stream after `bytes` bytes have been read, cutting off
+    /// the packet being received.
+    pub fn truncate_after(mut self, bytes: usize) -> Self {
+        self.truncate_after = Some(bytes);
+        self
+    }
+
+    /// Fail reads with [`ConnectionReset`](io::ErrorKind::ConnectionReset) after
+    /// `bytes` bytes have been read, like a server dropping the connection.
+    pub fn reset_after(mut self, bytes: usize) -> Self {
+        self.reset_after = Some(bytes);
+        self
+    }
+
+    /// Delay every write by `delay`, like a full send buffer.
+    pub fn stall_writes(mut self, delay: Duration) -> Self {
+        self.stall_writes = Some(delay);
+        self
+    }
+
+    /// The wrapped transport.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+
+    /// The number of bytes that may still be read before the stream is truncated or
+    /// reset.
+    fn read_budget(&self) -> Option<usize> {
+        [self.truncate_after, self.reset_after]
+            .iter()
+            .flatten()
+            .map(|limit| limit.saturating_sub(self.read))
+            .min()
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for FaultyIo<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let budget = this.read_budget();
+
+        if budget == Some(0) {
+            if matches!(this.reset_after, Some(limit) if this.read >= limit) {
+                return Poll::Ready(Err(io::ErrorKind::ConnectionReset.into()));
+            }
+            return Poll::Ready(Ok(()));
+        }
+
+        if let Some(latency) = this.latency {
+            let rng = &mut this.rng;
+            let delay = this.read_delay.get_or_insert_with(|| {
+                Box::pin(tokio::time::sleep(latency.mul_f64(rng.next_f64())))
+            });
+            ready!(delay.as_mut().poll(cx));
+        }
+
+        let mut len = buf.remaining();
+
+        if let Some(budget) = budget {
+            len = len.min(budget);
+        }
+        if this.split_reads && len > 1 {
+            len = 1 + (this.rng.next_u64() % len as u64) as usize;
+        }
+
+        let dst = buf.initialize_unfilled_to(len);
+        let mut limited = ReadBuf::new(dst);
+        ready!(Pin::new(&mut this.io).poll_read(cx, &mut limited))?;
+        let read = limited.filled().len();
+
+        for byte in &mut dst[..read] {
+            if this.corrupt > 0.0 && this.rng.next_f64() < this.corrupt {
+                *byte ^= 1 << (this.rng.next_u64() % 8);
+            }
+        }
+
+        buf.advance(read);
+        this.read += read;
+        this.read_delay = None;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for FaultyIo<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+
+        if let Some(stall) = this.stall_writes {
+            let delay = this
+                .write_delay
+                .get_or_insert_with(|| Box::pin(tokio::time::sleep(stall)));
+            ready!(delay.as_mut().poll(cx));
+        }
+
+        let written = ready!(Pin::new(&mut this.io).poll_write(cx, buf))?;
+        this.write_delay = None;
+
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
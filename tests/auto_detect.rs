@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use rcon::{Connection, Packet, PacketType, ServerProfile};
+use tokio::io::DuplexStream;
+
+mod common;
+
+/// How a stand-in server behaves during the handshake.
+#[derive(Clone, Copy)]
+enum Flavor {
+    /// Sends an empty `ResponseValue` before the `AuthResponse`.
+    Source,
+    /// Answers empty commands with this text.
+    Replies(&'static str),
+    /// Like [`Flavor::Replies`], but takes this long for every answer.
+    Slow(&'static str, Duration),
+    /// Never answers empty commands, and answers `/version` with a version number.
+    Factorio,
+    /// Never answers any command.
+    Silent,
+}
+
+fn answer(request: Packet, flavor: Flavor) -> Vec<Packet> {
+    let id = request.get_id();
+    let responses = match (request.get_type(), request.get_body(), flavor) {
+        (PacketType::Auth, _, Flavor::Source) => vec![
+            (PacketType::ResponseValue, Vec::new()),
+            (PacketType::AuthResponse, Vec::new()),
+        ],
+        (PacketType::Auth, _, _) => vec![(PacketType::AuthResponse, Vec::new())],
+        (_, _, Flavor::Silent) | (_, b"", Flavor::Factorio) => Vec::new(),
+        (_, b"/version", Flavor::Factorio) => {
+            vec![(PacketType::ResponseValue, b"1.1.110".to_vec())]
+        }
+        (_, b"", Flavor::Replies(reply)) | (_, b"", Flavor::Slow(reply, _)) => {
+            vec![(PacketType::ResponseValue, reply.into())]
+        }
+        (_, body, _) => vec![(PacketType::ResponseValue, body.to_vec())],
+    };
+
+    responses
+        .into_iter()
+        .map(|(ptype, body)| Packet::new(id, ptype, body))
+        .collect()
+}
+
+async fn detect(flavor: Flavor) -> Connection<DuplexStream> {
+    let (client, server) = tokio::io::duplex(4096);
+    let delay = match flavor {
+        Flavor::Slow(_, delay) => delay,
+        _ => Duration::ZERO,
+    };
+    tokio::spawn(common::serve_slowly(server, delay, move |request| {
+        Some(answer(request, flavor))
+    }));
+
+    <Connection<DuplexStream>>::builder()
+        .profile(ServerProfile::Ark)
+        .auto_detect()
+        .sleep_fn(tokio::time::sleep)
+        .handshake(client, "hunter2")
+        .await
+        .unwrap()
+}
+
+#[tokio::test(start_paused = true)]
+async fn detects_source_by_auth_preamble() {
+    let conn = detect(Flavor::Source).await;
+    assert!(matches!(conn.profile(), ServerProfile::Source));
+}
+
+#[tokio::test(start_paused = true)]
+async fn detects_minecraft_by_empty_command_reply() {
+    let conn = detect(Flavor::Replies(
+        "Unknown or incomplete command, see below for error<--[HERE]",
+    ))
+    .await;
+    assert!(matches!(conn.profile(), ServerProfile::Minecraft));
+}
+
+#[tokio::test(start_paused = true)]
+async fn detects_ark_by_empty_command_reply() {
+    let conn = detect(Flavor::Replies("Server received, But no response!! \n ")).await;
+    assert!(matches!(conn.profile(), ServerProfile::Ark));
+}
+
+#[tokio::test(start_paused = true)]
+async fn detects_factorio_by_missing_reply_and_version() {
+    let mut conn = detect(Flavor::Factorio).await;
+    assert!(matches!(conn.profile(), ServerProfile::Factorio));
+
+    // the unanswered probe must not confuse later commands
+    assert_eq!(conn.cmd("/version").await.unwrap(), "1.1.110");
+}
+
+#[tokio::test(start_paused = true)]
+async fn accepts_late_replies_from_slow_servers() {
+    let mut conn = detect(Flavor::Slow(
+        "Unknown or incomplete command, see below for error<--[HERE]",
+        Duration::from_millis(1500),
+    ))
+    .await;
+    assert!(matches!(conn.profile(), ServerProfile::Minecraft));
+
+    // the reply to the version probe arriving later is discarded
+    assert_eq!(conn.cmd("list").await.unwrap(), "list");
+}
+
+#[tokio::test(start_paused = true)]
+async fn silent_servers_are_generic() {
+    let conn = detect(Flavor::Silent).await;
+    assert!(matches!(conn.profile(), ServerProfile::Generic));
+}
+
+#[tokio::test(start_paused = true)]
+async fn falls_back_to_generic() {
+    let mut conn = detect(Flavor::Replies("")).await;
+    assert!(matches!(conn.profile(), ServerProfile::Generic));
+    assert_eq!(conn.cmd("list").await.unwrap(), "list");
+}
@@ -0,0 +1,202 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use rcon::{blocking, Error, Packet, PacketDecoder, PacketType, ServerProfile};
+
+/// A synchronous stand-in server that echoes commands in two packets.
+///
+/// It never answers `freeze` and the end marker following it.
+fn serve(mut io: TcpStream) {
+    let mut decoder = PacketDecoder::new();
+    let mut buf = [0; 4096];
+    let mut frozen = false;
+
+    loop {
+        let request = match decoder.decode().unwrap() {
+            Some(request) => request,
+            None => match io.read(&mut buf) {
+                Ok(0) | Err(_) => return,
+                Ok(read) => {
+                    decoder.feed(&buf[..read]);
+                    continue;
+                }
+            },
+        };
+        let id = request.get_id();
+
+        if std::mem::take(&mut frozen) {
+            continue;
+        }
+
+        let responses = match (request.get_type(), request.get_body()) {
+            (PacketType::Auth, b"hunter2") => vec![(id, PacketType::AuthResponse, Vec::new())],
+            (PacketType::Auth, _) => vec![(-1, PacketType::AuthResponse, Vec::new())],
+            (_, b"freeze") => {
+                frozen = true;
+                continue;
+            }
+            (_, body) => {
+                let (head, tail) = body.split_at(body.len() / 2);
+                vec![
+                    (id, PacketType::ResponseValue, head.to_vec()),
+                    (id, PacketType::ResponseValue, tail.to_vec()),
+                ]
+            }
+        };
+
+        let mut out = Vec::new();
+        for (id, ptype, body) in responses {
+            Packet::new(id, ptype, body).encode(&mut out);
+        }
+        if io.write_all(&out).is_err() {
+            return;
+        }
+    }
+}
+
+fn server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    thread::spawn(move || {
+        for io in listener.incoming() {
+            let io = io.unwrap();
+            thread::spawn(move || serve(io));
+        }
+    });
+
+    address
+}
+
+#[test]
+fn multi_packet_responses() {
+    let address = server();
+    let mut conn = blocking::Connection::connect(&address, "hunter2").unwrap();
+
+    assert_eq!(conn.cmd("say hello world").unwrap(), "say hello world");
+    assert_eq!(conn.cmd("list").unwrap(), "list");
+}
+
+#[test]
+fn wrong_password() {
+    let address = server();
+
+    assert!(matches!(
+        blocking::Connection::connect(&address, "hunter3"),
+        Err(Error::Auth)
+    ));
+}
+
+#[test]
+fn handshake_on_existing_transport() {
+    let address = server();
+    let io = TcpStream::connect(&address).unwrap();
+    let mut conn = blocking::Connection::builder()
+        .handshake_blocking(io, "hunter2")
+        .unwrap();
+
+    assert_eq!(conn.cmd("list").unwrap(), "list");
+}
+
+#[test]
+fn command_timeout() {
+    let address = server();
+    let mut conn = blocking::Connection::builder()
+        .command_timeout(Duration::from_millis(100))
+        .connect_blocking(&address, "hunter2")
+        .unwrap();
+
+    assert!(matches!(conn.cmd("freeze"), Err(Error::Timeout)));
+    assert_eq!(conn.cmd("list").unwrap(), "list");
+    assert!(matches!(
+        conn.cmd_with_timeout("freeze", Duration::from_millis(50)),
+        Err(Error::Timeout)
+    ));
+}
+
+#[test]
+fn timeouts_need_socket_read_timeouts() {
+    let address = server();
+    let io = TcpStream::connect(&address).unwrap();
+    let result = blocking::Connection::builder()
+        .auto_detect()
+        .handshake_blocking(io, "hunter2");
+
+    assert!(matches!(
+        result,
+        Err(Error::Io(err)) if err.kind() == std::io::ErrorKind::Unsupported
+    ));
+}
+
+#[test]
+fn auto_detect() {
+    let address = server();
+    // the empty probe is answered with two empty packets, which no profile is known by
+    let mut conn = blocking::Connection::builder()
+        .profile(ServerProfile::Minecraft)
+        .auto_detect()
+        .connect_blocking(&address, "hunter2")
+        .unwrap();
+
+    assert!(matches!(conn.profile(), ServerProfile::Generic));
+    assert_eq!(conn.cmd("list").unwrap(), "list");
+}
+
+/// Writes part of the next packet once `STALL` is set, then fails like a socket whose
+/// write timeout expired.
+struct Stalling {
+    io: TcpStream,
+    state: Arc<AtomicU8>,
+}
+
+const WRITE: u8 = 0;
+const STALL: u8 = 1;
+const STALLED: u8 = 2;
+
+impl Read for Stalling {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.io.read(buf)
+    }
+}
+
+impl Write for Stalling {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.state.load(Ordering::SeqCst) {
+            WRITE => self.io.write(buf),
+            STALL => {
+                self.state.store(STALLED, Ordering::SeqCst);
+                self.io.write(&buf[..buf.len().min(3)])
+            }
+            _ => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.io.flush()
+    }
+}
+
+#[test]
+fn failed_writes_break_the_connection() {
+    let address = server();
+    let state = Arc::new(AtomicU8::new(WRITE));
+    let io = Stalling {
+        io: TcpStream::connect(&address).unwrap(),
+        state: state.clone(),
+    };
+    let mut conn = blocking::Connection::builder()
+        .handshake_blocking(io, "hunter2")
+        .unwrap();
+    assert_eq!(conn.cmd("list").unwrap(), "list");
+
+    state.store(STALL, Ordering::SeqCst);
+    assert!(matches!(conn.cmd("list"), Err(Error::Timeout)));
+
+    // part of a packet has been written, so the connection cannot be used anymore
+    state.store(WRITE, Ordering::SeqCst);
+    assert!(matches!(conn.cmd("list"), Err(Error::Closed)));
+}
@@ -0,0 +1,90 @@
+use rcon::{Charset, Connection, Error};
+use tokio::io::DuplexStream;
+
+mod common;
+
+#[test]
+fn strict_utf8_rejects_invalid_bytes() {
+    assert!(matches!(
+        Charset::Utf8.decode(b"caf\xe9".to_vec()),
+        Err(Error::Decode(Charset::Utf8))
+    ));
+}
+
+#[test]
+fn lossy_utf8_replaces_invalid_bytes() {
+    assert_eq!(
+        Charset::Utf8Lossy.decode(b"caf\xe9".to_vec()).unwrap(),
+        "caf\u{FFFD}"
+    );
+}
+
+#[test]
+fn latin1_round_trip() {
+    assert_eq!(Charset::Latin1.decode(b"caf\xe9".to_vec()).unwrap(), "café");
+    assert_eq!(&*Charset::Latin1.encode("café").unwrap(), b"caf\xe9");
+    assert!(matches!(
+        Charset::Latin1.encode("5 €"),
+        Err(Error::Encode(Charset::Latin1))
+    ));
+}
+
+#[test]
+fn windows_1252_round_trip() {
+    let bytes = b"\x80 5 \x96 na\xefve \x93quoted\x94".to_vec();
+    let text = "€ 5 – naïve “quoted”";
+
+    assert_eq!(Charset::Windows1252.decode(bytes.clone()).unwrap(), text);
+    assert_eq!(&*Charset::Windows1252.encode(text).unwrap(), &bytes[..]);
+    assert!(matches!(
+        Charset::Windows1252.encode("李"),
+        Err(Error::Encode(Charset::Windows1252))
+    ));
+}
+
+#[test]
+fn charsets_are_parsed_by_name() {
+    assert_eq!("utf-8".parse(), Ok(Charset::Utf8));
+    assert_eq!("ISO-8859-1".parse(), Ok(Charset::Latin1));
+    assert_eq!("cp1252".parse(), Ok(Charset::Windows1252));
+
+    let err = "ebcdic".parse::<Charset>().unwrap_err();
+    assert_eq!(err.to_string(), "unknown charset `ebcdic`");
+}
+
+async fn connect(charset: Charset, parts: Vec<Vec<u8>>) -> Connection<DuplexStream> {
+    <Connection<DuplexStream>>::builder()
+        .charset(charset)
+        .handshake(common::spawn_split(parts), "hunter2")
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn cmd_bytes_returns_invalid_bytes_untouched() {
+    let response = b"caf\xe9 \xff\xfe\x00".to_vec();
+    let mut conn = connect(Charset::Utf8, vec![response.clone()]).await;
+
+    assert_eq!(conn.cmd_bytes("list").await.unwrap(), response);
+}
+
+#[tokio::test]
+async fn split_responses_are_decoded_after_reassembly() {
+    let latin1 = b"Gr\xfc\xdfe, caf\xe9".to_vec();
+    let utf8 = "Grüße, café".as_bytes().to_vec();
+
+    for (charset, response) in [(Charset::Latin1, latin1), (Charset::Utf8, utf8)] {
+        for offset in 0..=response.len() {
+            let parts = vec![response[..offset].to_vec(), response[offset..].to_vec()];
+            let mut conn = connect(charset, parts).await;
+
+            assert_eq!(
+                conn.cmd("list").await.unwrap(),
+                "Grüße, café",
+                "{} split at offset {}",
+                charset,
+                offset
+            );
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use rcon::chat::{tellraw, title, ClickEvent, Component, HoverEvent, TitleKind};
+use rcon::formatting::Color;
+use rcon::{Error, MINECRAFT_MAX_PAYLOAD_SIZE};
+
+#[test]
+fn contents_are_serialized() {
+    let component = Component::translate("chat.type.announcement")
+        .with(Component::selector("@p").separator(", ".into()).build())
+        .with(Component::score("@s", "kills").italic(false))
+        .build()
+        .append(Component::keybind("key.inventory").insertion("inv"));
+
+    assert_eq!(
+        component.to_json(),
+        r#"{"translate":"chat.type.announcement","with":[{"selector":"@p","separator":{"text":", "}},{"score":{"name":"@s","objective":"kills"},"italic":false}],"extra":[{"keybind":"key.inventory","insertion":"inv"}]}"#
+    );
+}
+
+#[test]
+fn styles_and_events_are_serialized() {
+    let component = Component::text("")
+        .append(
+            Component::text("sword")
+                .color(Color::Rgb(0xff, 0x80, 0x0a))
+                .underlined(true)
+                .strikethrough(true)
+                .obfuscated(false)
+                .hover(HoverEvent::ShowItem {
+                    id: "minecraft:diamond_sword".to_owned(),
+                    count: Some(1),
+                }),
+        )
+        .append(
+            Component::text("pig")
+                .color(Color::LightPurple)
+                .click(ClickEvent::CopyToClipboard("Babe".to_owned()))
+                .hover(HoverEvent::ShowEntity {
+                    kind: "minecraft:pig".to_owned(),
+                    id: "d8fb2a4e-3c6b-4d1c-9b8e-7a0e4e5c1f23".to_owned(),
+                    name: Some(Box::new("Babe".into())),
+                }),
+        );
+
+    assert_eq!(
+        component.to_string(),
+        concat!(
+            r#"{"text":"","extra":["#,
+            r##"{"text":"sword","color":"#ff800a","underlined":true,"strikethrough":true,"obfuscated":false,"##,
+            r#""hoverEvent":{"action":"show_item","contents":{"id":"minecraft:diamond_sword","count":1}}},"#,
+            r#"{"text":"pig","color":"light_purple","clickEvent":{"action":"copy_to_clipboard","value":"Babe"},"#,
+            r#""hoverEvent":{"action":"show_entity","contents":{"type":"minecraft:pig","id":"d8fb2a4e-3c6b-4d1c-9b8e-7a0e4e5c1f23","name":{"text":"Babe"}}}}"#,
+            r#"]}"#
+        )
+    );
+}
+
+#[test]
+fn commands_are_escaped() {
+    let message = Component::text("say \"hi\"\n\\o/");
+
+    assert_eq!(
+        tellraw("@a[tag=vip]", &message).unwrap(),
+        r#"tellraw @a[tag=vip] {"text":"say \"hi\"\n\\o/"}"#
+    );
+    assert_eq!(
+        title("Steve", TitleKind::Actionbar, &"Welcome".into()).unwrap(),
+        r#"title Steve actionbar {"text":"Welcome"}"#
+    );
+}
+
+#[test]
+fn long_commands_are_rejected() {
+    // `tellraw @a {"text":""}` is 22 bytes
+    let fits = Component::text("x".repeat(MINECRAFT_MAX_PAYLOAD_SIZE - 22));
+    let too_long = Component::text("x".repeat(MINECRAFT_MAX_PAYLOAD_SIZE - 21));
+
+    assert_eq!(
+        tellraw("@a", &fits).unwrap().len(),
+        MINECRAFT_MAX_PAYLOAD_SIZE
+    );
+    assert!(matches!(
+        tellraw("@a", &too_long),
+        Err(Error::CommandTooLong)
+    ));
+    assert!(matches!(
+        title("@a", TitleKind::Title, &too_long),
+        Err(Error::CommandTooLong)
+    ));
+}
+
+#[test]
+fn builders_convert_to_components() {
+    let translated: Component = Component::translate("multiplayer.player.joined")
+        .with("Steve".into())
+        .into();
+    let selector: Component = Component::selector("@a").into();
+
+    assert_eq!(
+        translated.bold(true).to_json(),
+        r#"{"translate":"multiplayer.player.joined","with":[{"text":"Steve"}],"bold":true}"#
+    );
+    assert_eq!(selector.to_json(), r#"{"selector":"@a"}"#);
+}
@@ -0,0 +1,283 @@
+use std::io::Write;
+use std::net::SocketAddr;
+use std::process::{Command, Output, Stdio};
+use std::time::Duration;
+
+use rcon::testing::{Flavor, MockHandle, MockServer, Reply};
+use serde_json::Value;
+
+async fn server() -> (SocketAddr, MockHandle) {
+    MockServer::new("hunter2")
+        .flavor(Flavor::Minecraft)
+        .respond("list", Reply::text("There are 0 players"))
+        .respond("seed", Reply::text("Seed: [42]"))
+        .respond("say a\nb", Reply::text(""))
+        .respond("motd", Reply::text("§aWelcome§r to §lthe lobby"))
+        .respond("clear", Reply::text("\x1b[2J\x07cleared"))
+        .respond(
+            "stop",
+            Reply::text("Stopping").delayed(Duration::from_secs(10)),
+        )
+        .listen()
+        .await
+        .unwrap()
+}
+
+/// Run the CLI against `address` with `args`, feeding it `stdin`.
+async fn rcon(address: SocketAddr, args: &[&str], stdin: &str) -> Output {
+    let port = address.port().to_string();
+    let args = [&["-H", "127.0.0.1", "-P", &port][..], args].concat();
+
+    run(&args, Some("hunter2"), stdin).await
+}
+
+/// Run the CLI with `args` and the password from the environment, feeding it `stdin`.
+async fn run(args: &[&str], password: Option<&str>, stdin: &str) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rcon"));
+    if let Some(password) = password {
+        command.env("RCON_PASSWORD", password);
+    }
+
+    let mut child = command
+        .args(args)
+        .env_remove("RCON_CONFIG")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+
+    tokio::task::spawn_blocking(move || child.wait_with_output().unwrap())
+        .await
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[tokio::test]
+async fn one_shot_commands() {
+    let (address, handle) = server().await;
+
+    for args in [&["list", "seed"][..], &["--minecraft", "list", "seed"]] {
+        let output = rcon(address, args, "").await;
+
+        assert_eq!(stdout(&output), "There are 0 players\nSeed: [42]\n");
+    }
+
+    handle.assert_done();
+}
+
+#[tokio::test]
+async fn commands_from_stdin() {
+    let (address, handle) = server().await;
+    let script = "list\n\n# the seed\nseed\nsay a\\\nb\n";
+
+    let output = rcon(address, &["--profile", "minecraft"], script).await;
+
+    assert_eq!(stdout(&output), "There are 0 players\nSeed: [42]\n");
+    assert_eq!(handle.received(), ["list", "seed", "say a\nb"]);
+}
+
+#[tokio::test]
+async fn commands_from_file() {
+    let (address, handle) = server().await;
+    let path = std::env::temp_dir().join(format!("rcon-cli-test-{}", std::process::id()));
+    std::fs::write(&path, "seed\nlist\n").unwrap();
+
+    let output = rcon(address, &["-f", path.to_str().unwrap()], "").await;
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(stdout(&output), "Seed: [42]\nThere are 0 players\n");
+    handle.assert_done();
+}
+
+#[tokio::test]
+async fn errors_fail_the_process() {
+    let (address, _) = server().await;
+
+    let output = rcon(address, &["-p", "hunter3", "list"], "").await;
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "rcon: authentication failed\n"
+    );
+
+    let output = rcon(address, &["--profile", "quake", "list"], "").await;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown server profile `quake`"));
+}
+
+#[tokio::test]
+async fn formatting_codes_are_stripped() {
+    let (address, _) = server().await;
+
+    let output = rcon(address, &["motd"], "").await;
+    assert_eq!(stdout(&output), "Welcome to the lobby\n");
+
+    let output = rcon(address, &["--output", "raw", "motd"], "").await;
+    assert_eq!(stdout(&output), "§aWelcome§r to §lthe lobby\n");
+
+    let output = rcon(address, &["clear"], "").await;
+    assert_eq!(stdout(&output), "[2Jcleared\n");
+}
+
+#[tokio::test]
+async fn ndjson_output() {
+    let (address, _) = server().await;
+
+    let output = rcon(address, &["-o", "ndjson"], "list\nmotd\n").await;
+    let records: Vec<Value> = stdout(&output)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["target"], "127.0.0.1");
+    assert_eq!(records[0]["command"], "list");
+    assert_eq!(records[0]["response"], "There are 0 players");
+    assert_eq!(records[0]["packets"], 2);
+    assert!(records[0]["duration_ms"].as_f64().unwrap() >= 0.0);
+    assert_eq!(records[0]["error"], Value::Null);
+    assert_eq!(records[1]["response"], "§aWelcome§r to §lthe lobby");
+}
+
+#[tokio::test]
+async fn json_output_includes_errors() {
+    let (address, _) = server().await;
+
+    let output = rcon(
+        address,
+        &["-o", "json", "--timeout", "0.2", "seed", "stop"],
+        "",
+    )
+    .await;
+    assert!(!output.status.success());
+
+    let records: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(records[0]["response"], "Seed: [42]");
+    assert_eq!(records[1]["command"], "stop");
+    assert_eq!(records[1]["response"], Value::Null);
+    assert_eq!(records[1]["packets"], Value::Null);
+    assert_eq!(records[1]["error"]["kind"], "timeout");
+    assert_eq!(records.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn json_output_continues_after_failures() {
+    let (address, handle) = server().await;
+    // nothing listens on the port of a closed listener
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let closed_port = closed.local_addr().unwrap().port();
+    drop(closed);
+
+    let path = std::env::temp_dir().join(format!("rcon-cli-json-config-{}", std::process::id()));
+    let config = format!(
+        "[servers.lobby]\nhost = '127.0.0.1'\nport = {port}\npassword = 'hunter2'\n\
+         [servers.down]\nhost = '127.0.0.1'\nport = {closed_port}\npassword = 'hunter2'\n\
+         [servers.locked]\nhost = '127.0.0.1'\nport = {port}\npassword = 'hunter3'\n\
+         [groups]\nall = ['down', 'lobby', 'locked']\n",
+        port = address.port(),
+        closed_port = closed_port,
+    );
+    std::fs::write(&path, config).unwrap();
+    // too long for Minecraft
+    let long = "x".repeat(2000);
+
+    let output = run(
+        &[
+            "--config",
+            path.to_str().unwrap(),
+            "-s",
+            "all",
+            "-o",
+            "ndjson",
+            &long,
+            "seed",
+        ],
+        None,
+        "",
+    )
+    .await;
+    std::fs::remove_file(&path).unwrap();
+    assert!(!output.status.success());
+
+    let records: Vec<Value> = std::str::from_utf8(&output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let summary: Vec<_> = records
+        .iter()
+        .map(|record| {
+            (
+                record["target"].as_str().unwrap(),
+                record["command"].as_str(),
+                record["error"]["kind"].as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("down", None, Some("io")),
+            ("locked", None, Some("auth")),
+            ("lobby", Some(long.as_str()), Some("command_too_long")),
+            ("lobby", Some("seed"), None),
+        ]
+    );
+    assert_eq!(records[3]["response"], "Seed: [42]");
+    assert_eq!(handle.received(), ["seed"]);
+}
+
+#[tokio::test]
+async fn servers_from_the_config_file() {
+    let (address, handle) = server().await;
+    let path = std::env::temp_dir().join(format!("rcon-cli-config-{}", std::process::id()));
+    let config = format!(
+        "[servers.lobby]\nhost = '127.0.0.1'\nport = {port}\npassword = 'hunter2'\n\
+         [servers.survival]\nhost = '127.0.0.1'\nport = {port}\n\
+         password = {{ env = 'RCON_CLI_TEST_PASSWORD' }}\nprofile = 'minecraft'\n\
+         [groups]\nall = ['lobby', 'survival']\n",
+        port = address.port()
+    );
+    std::fs::write(&path, config).unwrap();
+    let path = path.to_str().unwrap();
+
+    let output = run(&["--config", path, "-s", "lobby", "seed"], None, "").await;
+    assert_eq!(stdout(&output), "Seed: [42]\n");
+
+    let output = run(
+        &["--config", path, "-s", "all", "list"],
+        Some("hunter2"),
+        "",
+    )
+    .await;
+    assert_eq!(
+        stdout(&output),
+        "[lobby]\nThere are 0 players\n[survival]\nThere are 0 players\n"
+    );
+
+    let output = run(&["--config", path, "-s", "survival", "seed"], None, "").await;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot get the password"));
+
+    let output = run(&["--config", path, "-s", "creative", "seed"], None, "").await;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no server or group named `creative`"));
+
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(handle.received(), ["seed", "list", "list"]);
+}
@@ -0,0 +1,141 @@
+use rcon::{
+    Client, Connection, Driver, Error, Packet, PacketType, SinglePacket, UnexpectedPacketPolicy,
+};
+use tokio::io::DuplexStream;
+
+mod common;
+
+/// A stand-in server answering the handshake, and commands in batches of `batch`
+/// with the packets `answer` returns for them.
+///
+/// Commands that are not pipelined never get a response.
+fn batches<F>(batch: usize, mut answer: F) -> DuplexStream
+where
+    F: FnMut(&[Packet]) -> Vec<Packet> + Send + 'static,
+{
+    let mut requests = Vec::new();
+
+    common::spawn(move |request| {
+        if request.get_type() == PacketType::Auth {
+            return Some(vec![common::echo(&request)]);
+        }
+
+        requests.push(request);
+
+        if requests.len() < batch {
+            return Some(Vec::new());
+        }
+
+        let responses = answer(&requests);
+        requests.clear();
+        Some(responses)
+    })
+}
+
+async fn connect(batch: usize) -> Connection<DuplexStream> {
+    let io = batches(batch, |requests| {
+        requests.iter().map(common::echo).collect()
+    });
+
+    <Connection<DuplexStream>>::builder()
+        .handshake(io, "hunter2")
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn commands_are_pipelined() {
+    // two commands and their end markers
+    let (client, driver) = connect(4).await.into_client();
+    let driver = tokio::spawn(driver.run());
+
+    let (first, second) = tokio::join!(client.cmd("first"), client.cmd("second"));
+    assert_eq!(first.unwrap(), "first");
+    assert_eq!(second.unwrap(), "second");
+
+    drop(client);
+    driver.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn responses_are_routed_to_their_commands() {
+    let (client, driver) = connect(1).await.into_client();
+    tokio::spawn(driver.run());
+
+    let tasks: Vec<_> = (0..50)
+        .map(|i| {
+            let client = client.clone();
+            tokio::spawn(async move { (i, client.cmd(&format!("echo {}", i)).await) })
+        })
+        .collect();
+
+    for task in tasks {
+        let (i, response) = task.await.unwrap();
+        assert_eq!(response.unwrap(), format!("echo {}", i));
+    }
+}
+
+#[tokio::test]
+async fn commands_fail_once_the_driver_stopped() {
+    let (client, driver) = connect(1).await.into_client();
+    drop(driver);
+
+    assert!(client.is_closed());
+    assert!(matches!(client.cmd("list").await, Err(Error::Closed)));
+}
+
+async fn connect_noisy(policy: UnexpectedPacketPolicy) -> (Client, Driver<DuplexStream>) {
+    // every two commands are answered at once, preceded by a stray packet and an
+    // `AuthResponse` with the id of the second command
+    let io = batches(2, |requests| {
+        let mut responses = vec![
+            common::stray(),
+            Packet::new(requests[1].get_id(), PacketType::AuthResponse, Vec::new()),
+        ];
+        responses.extend(requests.iter().map(common::echo));
+        responses
+    });
+
+    <Connection<DuplexStream>>::builder()
+        .response_terminator(SinglePacket)
+        .unexpected_packet_policy(policy)
+        .handshake(io, "hunter2")
+        .await
+        .unwrap()
+        .into_client()
+}
+
+#[tokio::test]
+async fn unexpected_packets_do_not_stop_the_driver() {
+    let (client, driver) = connect_noisy(UnexpectedPacketPolicy::default()).await;
+    let driver = tokio::spawn(driver.run());
+
+    let (first, second) = tokio::join!(client.cmd("first"), client.cmd("second"));
+    assert_eq!(first.unwrap(), "first");
+    assert_eq!(second.unwrap(), "second");
+
+    drop(client);
+    driver.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn unexpected_packets_only_fail_their_command() {
+    let (client, driver) = connect_noisy(UnexpectedPacketPolicy::Error).await;
+    tokio::spawn(driver.run());
+
+    let (first, second) = tokio::join!(client.cmd("first"), client.cmd("second"));
+    assert_eq!(first.unwrap(), "first");
+    assert!(matches!(
+        second,
+        Err(Error::UnexpectedPacket {
+            ptype: PacketType::AuthResponse,
+            ..
+        })
+    ));
+
+    // the late response of the failed command is discarded, and the driver keeps going
+    let (third, fourth) = tokio::join!(client.cmd("third"), client.cmd("fourth"));
+    assert_eq!(third.unwrap(), "third");
+    assert!(matches!(fourth, Err(Error::UnexpectedPacket { .. })));
+    assert!(!client.is_closed());
+}
@@ -0,0 +1,35 @@
+#![cfg(feature = "codec")]
+
+use futures_util::{SinkExt, StreamExt};
+use rcon::{Packet, PacketType, RconCodec};
+use tokio_util::codec::Framed;
+
+#[tokio::test]
+async fn framed_round_trip() {
+    let (client, server) = tokio::io::duplex(4096);
+    let mut client = Framed::new(client, RconCodec::new());
+    let mut server = Framed::new(server, RconCodec::new());
+
+    let long_body = "x".repeat(200);
+
+    client
+        .send(Packet::new(1, PacketType::ExecCommand, "list".into()))
+        .await
+        .unwrap();
+    server
+        .send(Packet::new(
+            1,
+            PacketType::ResponseValue,
+            long_body.clone().into_bytes(),
+        ))
+        .await
+        .unwrap();
+
+    let request = server.next().await.unwrap().unwrap();
+    assert_eq!(request.get_id(), 1);
+    assert_eq!(request.get_body(), b"list");
+
+    let response = client.next().await.unwrap().unwrap();
+    assert_eq!(response.get_type(), PacketType::ResponseValue);
+    assert_eq!(response.get_body(), long_body.as_bytes());
+}
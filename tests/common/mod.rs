@@ -0,0 +1,113 @@
+//! Stand-in servers and helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use rcon::{Packet, PacketType};
+use tokio::io::DuplexStream;
+
+/// Encode a packet the way it is sent over the wire.
+pub fn encode(id: i32, ptype: PacketType, body: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    Packet::new(id, ptype, body.to_vec()).encode(&mut buf);
+    buf
+}
+
+/// The packet a stand-in server answers `request` with when it accepts every
+/// password and echoes every command.
+pub fn echo(request: &Packet) -> Packet {
+    let ptype = match request.get_type() {
+        PacketType::Auth => PacketType::AuthResponse,
+        _ => PacketType::ResponseValue,
+    };
+
+    Packet::new(request.get_id(), ptype, request.get_body().to_vec())
+}
+
+/// Spawn a stand-in server answering requests with [`serve`], and return the client's
+/// end of the connection.
+pub fn spawn<F>(answer: F) -> DuplexStream
+where
+    F: FnMut(Packet) -> Option<Vec<Packet>> + Send + 'static,
+{
+    let (client, server) = tokio::io::duplex(4096);
+    tokio::spawn(serve(server, answer));
+    client
+}
+
+/// Spawn a stand-in server answering every command with `parts`, one packet each,
+/// followed by the response to the end marker of the command.
+pub fn spawn_split(parts: Vec<Vec<u8>>) -> DuplexStream {
+    let mut cmd = None;
+
+    spawn(move |request| {
+        if request.get_type() == PacketType::Auth {
+            return Some(vec![echo(&request)]);
+        }
+
+        let id = match cmd.take() {
+            Some(id) => id,
+            None => {
+                cmd = Some(request.get_id());
+                return Some(Vec::new());
+            }
+        };
+        let mut responses: Vec<_> = parts
+            .iter()
+            .map(|part| Packet::new(id, PacketType::ResponseValue, part.clone()))
+            .collect();
+        responses.push(Packet::new(
+            request.get_id(),
+            PacketType::ResponseValue,
+            Vec::new(),
+        ));
+
+        Some(responses)
+    })
+}
+
+/// A stand-in server answering every request, including the authentication request,
+/// with the packets returned by `answer`.
+///
+/// It hangs up once `answer` returns `None` or the client hung up.
+pub async fn serve<F>(io: DuplexStream, answer: F)
+where
+    F: FnMut(Packet) -> Option<Vec<Packet>>,
+{
+    serve_slowly(io, Duration::ZERO, answer).await
+}
+
+/// Like [`serve`], but takes `delay` to answer every request except the
+/// authentication request.
+pub async fn serve_slowly<F>(mut io: DuplexStream, delay: Duration, mut answer: F)
+where
+    F: FnMut(Packet) -> Option<Vec<Packet>>,
+{
+    loop {
+        let request = match Packet::deserialize(&mut io).await {
+            Ok(request) => request,
+            Err(_) => return,
+        };
+        let auth = request.get_type() == PacketType::Auth;
+        let responses = match answer(request) {
+            Some(responses) => responses,
+            None => return,
+        };
+
+        if !auth && !delay.is_zero() && !responses.is_empty() {
+            tokio::time::sleep(delay).await;
+        }
+
+        for response in responses {
+            if response.serialize(&mut io).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// A response with the id -7, which is never used for commands.
+pub fn stray() -> Packet {
+    Packet::new(-7, PacketType::ResponseValue, b"stray".to_vec())
+}
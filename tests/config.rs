@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use rcon::config::{Config, ConfigError, Password};
+use rcon::testing::{Flavor, MockServer, Reply};
+use rcon::{Builder, Charset, ServerProfile};
+use tokio::io::DuplexStream;
+
+const CONFIG: &str = r#"
+[servers.lobby]
+host = "mc.example.com"
+password = "hunter2"
+profile = "Minecraft"
+charset = "latin1"
+command_timeout = 2.5
+
+[servers.factory]
+host = "factorio.example.com"
+port = 27015
+password = { env = "FACTORY_RCON_PASSWORD" }
+
+[groups]
+all = ["lobby", "factory"]
+broken = ["lobby", "survival"]
+"#;
+
+#[test]
+fn servers_are_parsed() {
+    let config: Config = CONFIG.parse().unwrap();
+
+    let lobby = config.server("lobby").unwrap();
+    assert_eq!(lobby.address(), ("mc.example.com", 25575));
+    assert!(matches!(lobby.profile, Some(ServerProfile::Minecraft)));
+    assert_eq!(lobby.charset, Some(Charset::Latin1));
+    assert_eq!(lobby.command_timeout, Some(Duration::from_millis(2500)));
+    assert_eq!(lobby.connect_timeout, None);
+
+    let factory = config.server("factory").unwrap();
+    assert_eq!(factory.address(), ("factorio.example.com", 27015));
+    assert!(factory.profile.is_none());
+}
+
+#[test]
+fn invalid_configs_are_rejected() {
+    for config in [
+        "[servers.lobby]\nhost = \"localhost\"",
+        "[servers.lobby]\nhost = \"localhost\"\npassword = \"\"\nprofile = \"quake\"",
+        "[servers.lobby]\nhost = \"localhost\"\npassword = \"\"\nprot = 25575",
+        "[servers.lobby]\nhost = \"localhost\"\npassword = { vault = \"rcon\" }",
+        "[servers.lobby]\nhost = \"localhost\"\npassword = { env = \"X\", file = \"y\" }",
+        "[servers.lobby]\nhost = \"localhost\"\npassword = { env = \"X\", fiel = \"y\" }",
+        "[servers.lobby]\nhost = \"localhost\"\npassword = {}",
+    ] {
+        assert!(
+            matches!(config.parse::<Config>(), Err(ConfigError::Parse(_))),
+            "{}",
+            config
+        );
+    }
+
+    let err = "[servers.lobby]\nhost = \"localhost\"\npassword = \"\"\nprofile = \"quake\""
+        .parse::<Config>()
+        .unwrap_err();
+    assert!(err.to_string().contains("unknown server profile `quake`"));
+
+    let err = "[servers.lobby]\nhost = \"localhost\"\npassword = { evn = \"X\" }"
+        .parse::<Config>()
+        .unwrap_err();
+    assert!(err.to_string().contains("unknown field `evn`"), "{}", err);
+}
+
+#[test]
+fn groups_are_resolved() {
+    let config: Config = CONFIG.parse().unwrap();
+
+    let names = |name| -> Vec<String> {
+        let targets = config.targets(name).unwrap();
+        targets.iter().map(|(name, _)| name.to_string()).collect()
+    };
+    assert_eq!(names("all"), ["lobby", "factory"]);
+    assert_eq!(names("factory"), ["factory"]);
+
+    assert!(matches!(
+        config.targets("broken"),
+        Err(ConfigError::UnknownServer(name)) if name == "survival"
+    ));
+    assert!(matches!(
+        config.server("all"),
+        Err(ConfigError::UnknownServer(name)) if name == "all"
+    ));
+}
+
+#[test]
+fn passwords_are_resolved() {
+    let path = std::env::temp_dir().join(format!("rcon-config-test-{}", std::process::id()));
+    std::fs::write(&path, "from a file\n").unwrap();
+
+    let password = Password::File { file: path.clone() };
+    assert_eq!(password.resolve().unwrap(), "from a file");
+    std::fs::remove_file(&path).unwrap();
+    assert!(password.resolve().is_err());
+
+    std::env::set_var("RCON_CONFIG_TEST_PASSWORD", "from the environment");
+    let password = Password::Env {
+        env: "RCON_CONFIG_TEST_PASSWORD".to_owned(),
+    };
+    assert_eq!(password.resolve().unwrap(), "from the environment");
+
+    let password = Password::Env {
+        env: "RCON_CONFIG_TEST_UNSET".to_owned(),
+    };
+    assert!(password.resolve().is_err());
+}
+
+#[test]
+fn builders_come_with_their_server() {
+    let dir = std::env::temp_dir().join(format!("rcon-config-home-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("rcon")).unwrap();
+    std::fs::write(dir.join("rcon").join("servers.toml"), CONFIG).unwrap();
+    std::env::set_var("XDG_CONFIG_HOME", &dir);
+
+    let (_, server) = Builder::<DuplexStream>::from_config("lobby").unwrap();
+    assert_eq!(server.address(), ("mc.example.com", 25575));
+    assert_eq!(server.password().unwrap(), "hunter2");
+    assert!(Builder::<DuplexStream>::from_config("survival").is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn password_commands_are_run() {
+    let password = Password::Command {
+        command: "echo from a command".to_owned(),
+    };
+    assert_eq!(password.resolve().unwrap(), "from a command");
+
+    let password = Password::Command {
+        command: "echo oops; false".to_owned(),
+    };
+    assert!(password.resolve().is_err());
+}
+
+#[test]
+fn inline_passwords_are_not_printed() {
+    let config: Config = CONFIG.parse().unwrap();
+
+    assert!(!format!("{:?}", config).contains("hunter2"));
+    assert!(format!("{:?}", config).contains("FACTORY_RCON_PASSWORD"));
+}
+
+#[tokio::test]
+async fn builders_use_the_server_settings() {
+    let config: Config = "
+        [servers.lobby]
+        host = 'localhost'
+        password = 'hunter2'
+
+        [servers.factory]
+        host = 'localhost'
+        password = 'hunter2'
+        profile = 'factorio'
+    "
+    .parse()
+    .unwrap();
+
+    for (name, flavor, profile) in [
+        ("lobby", Flavor::Minecraft, ServerProfile::Minecraft),
+        ("factory", Flavor::Factorio, ServerProfile::Factorio),
+    ] {
+        let server = config.server(name).unwrap();
+        let (io, handle) = MockServer::new("hunter2")
+            .flavor(flavor)
+            .respond("list", Reply::text("There are 0 players"))
+            .spawn();
+        let mut conn = server
+            .builder::<DuplexStream>()
+            .sleep_fn(tokio::time::sleep)
+            .handshake(io, &server.password().unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(format!("{:?}", conn.profile()), format!("{:?}", profile));
+        assert_eq!(conn.cmd("list").await.unwrap(), "There are 0 players");
+        handle.assert_done();
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use rcon::{
+    ClientSession, Connection, Error, Event, Packet, PacketType, SinglePacket,
+    UnexpectedPacketPolicy,
+};
+use tokio::io::DuplexStream;
+
+mod common;
+
+use common::encode;
+
+#[test]
+fn responses_are_matched_by_id() {
+    let mut session = ClientSession::new().response_terminator(Arc::new(SinglePacket));
+    let first = session.command("first");
+    let second = session.command("second");
+
+    session
+        .receive(&encode(second, PacketType::ResponseValue, b"2"))
+        .unwrap();
+    session
+        .receive(&encode(first, PacketType::ResponseValue, b"1"))
+        .unwrap();
+
+    assert_eq!(
+        session.poll_event(),
+        Some(Event::Response {
+            id: second,
+            body: b"2".to_vec()
+        })
+    );
+    assert_eq!(
+        session.poll_event(),
+        Some(Event::Response {
+            id: first,
+            body: b"1".to_vec()
+        })
+    );
+}
+
+#[test]
+fn stray_packets_are_reported() {
+    let mut session = ClientSession::new();
+    let id = session.command("list");
+
+    session
+        .receive(&encode(id + 100, PacketType::ResponseValue, b"stray"))
+        .unwrap();
+    session
+        .receive(&encode(id, PacketType::AuthResponse, b""))
+        .unwrap();
+
+    assert_eq!(
+        session.poll_event(),
+        Some(Event::UnexpectedPacket {
+            id: id + 100,
+            ptype: PacketType::ResponseValue
+        })
+    );
+    assert_eq!(
+        session.poll_event(),
+        Some(Event::UnexpectedPacket {
+            id,
+            ptype: PacketType::AuthResponse
+        })
+    );
+}
+
+#[test]
+fn late_packets_of_cancelled_commands_are_discarded() {
+    let mut session = ClientSession::new();
+    let cancelled = session.command("slow");
+    session.cancel(cancelled);
+    let id = session.command("list");
+
+    for packet in [
+        encode(cancelled, PacketType::ResponseValue, b"too late"),
+        encode(cancelled + 1, PacketType::ResponseValue, b""),
+        encode(id, PacketType::ResponseValue, b"players"),
+        encode(id + 1, PacketType::ResponseValue, b""),
+    ] {
+        session.receive(&packet).unwrap();
+    }
+
+    assert_eq!(
+        session.poll_event(),
+        Some(Event::Response {
+            id,
+            body: b"players".to_vec()
+        })
+    );
+    assert_eq!(session.poll_event(), None);
+}
+
+async fn connect(policy: UnexpectedPacketPolicy) -> Connection<DuplexStream> {
+    // every response is preceded by a stray packet
+    let io = common::spawn(|request| {
+        let response = match request.get_type() {
+            PacketType::Auth => common::echo(&request),
+            _ => Packet::new(request.get_id(), PacketType::ResponseValue, b"ok".to_vec()),
+        };
+        Some(vec![common::stray(), response])
+    });
+
+    <Connection<DuplexStream>>::builder()
+        .response_terminator(SinglePacket)
+        .unexpected_packet_policy(policy)
+        .handshake(io, "hunter2")
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn unexpected_packets_can_fail_the_command() {
+    // the handshake skips them regardless of the policy
+    let mut conn = connect(UnexpectedPacketPolicy::Error).await;
+
+    assert!(matches!(
+        conn.cmd("list").await,
+        Err(Error::UnexpectedPacket {
+            id: -7,
+            ptype: PacketType::ResponseValue
+        })
+    ));
+}
+
+#[tokio::test]
+async fn unexpected_packets_are_skipped_by_default() {
+    let mut conn = connect(UnexpectedPacketPolicy::default()).await;
+
+    assert_eq!(conn.cmd("list").await.unwrap(), "ok");
+    assert_eq!(conn.cmd("list").await.unwrap(), "ok");
+}
@@ -0,0 +1,65 @@
+use rcon::{Connection, Packet, PacketType};
+use tokio::io::DuplexStream;
+
+mod common;
+
+const PLAYERS: usize = 500;
+
+fn players() -> String {
+    let mut players = format!("Players ({}):\n", PLAYERS);
+
+    for i in 0..PLAYERS {
+        players += &format!("  engineer_{} (online)\n", i);
+    }
+
+    players
+}
+
+/// A stand-in for a Factorio server.
+///
+/// Like Factorio it never answers empty commands, echoes request ids and
+/// splits long responses into several packets.
+fn factorio(request: Packet) -> Option<Vec<Packet>> {
+    let (ptype, response) = match (request.get_type(), request.get_body()) {
+        (PacketType::Auth, b"factorio") => (PacketType::AuthResponse, String::new()),
+        (_, b"") => return Some(Vec::new()),
+        (_, b"/version") => (PacketType::ResponseValue, "1.1.110\n".to_owned()),
+        (_, b"/players") => (PacketType::ResponseValue, players()),
+        (_, b"/time") => (PacketType::ResponseValue, "2 hours 3 minutes\n".to_owned()),
+        (_, cmd) => (
+            PacketType::ResponseValue,
+            format!("Unknown command \"{}\".\n", String::from_utf8_lossy(cmd)),
+        ),
+    };
+
+    let mut responses: Vec<_> = response
+        .as_bytes()
+        .chunks(4096)
+        .map(|chunk| Packet::new(request.get_id(), ptype, chunk.to_vec()))
+        .collect();
+
+    if response.is_empty() {
+        responses.push(Packet::new(request.get_id(), ptype, Vec::new()));
+    }
+
+    Some(responses)
+}
+
+#[tokio::test]
+async fn multi_packet_responses_are_complete() {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(common::serve(server, factorio));
+
+    let mut conn = <Connection<DuplexStream>>::builder()
+        .enable_factorio_quirks(true)
+        .handshake(client, "factorio")
+        .await
+        .unwrap();
+
+    let players = conn.cmd("/players").await.unwrap();
+    assert!(players.len() > 4096);
+    assert_eq!(players, self::players());
+
+    // the next command must not receive leftovers of the previous response
+    assert_eq!(conn.cmd("/time").await.unwrap(), "2 hours 3 minutes\n");
+}
@@ -0,0 +1,191 @@
+use std::io;
+use std::time::Duration;
+
+use rcon::testing::{FaultyIo, Flavor, MockServer, Reply};
+use rcon::{Connection, Error, Packet, PacketType};
+use tokio::io::{AsyncReadExt, DuplexStream};
+
+mod common;
+
+const SEEDS: u64 = 50;
+
+const PACKETS: &[(i32, PacketType, &[u8])] = &[
+    (1, PacketType::AuthResponse, b""),
+    (
+        2,
+        PacketType::ResponseValue,
+        "Jürgen, 李雷, Zoë 🦀".as_bytes(),
+    ),
+    (3, PacketType::ResponseValue, &[b'x'; 5000]),
+];
+
+fn bytes() -> Vec<u8> {
+    PACKETS
+        .iter()
+        .flat_map(|&(id, ptype, body)| common::encode(id, ptype, body))
+        .collect()
+}
+
+#[tokio::test(start_paused = true)]
+async fn deserialize_split_and_delayed_reads() {
+    let bytes = bytes();
+
+    for seed in 0..SEEDS {
+        let mut io = FaultyIo::new(&bytes[..], seed)
+            .split_reads()
+            .latency(Duration::from_millis(50));
+
+        for &(id, _, body) in PACKETS {
+            let packet = Packet::deserialize(&mut io).await.unwrap();
+
+            assert_eq!(packet.get_id(), id, "seed {}", seed);
+            assert_eq!(packet.get_body(), body, "seed {}", seed);
+        }
+    }
+}
+
+#[tokio::test]
+async fn deserialize_truncated_packet() {
+    let bytes = bytes();
+
+    // the first packet is 14 bytes long
+    for bytes_read in [0, 3, 4, 12, 13] {
+        let mut io = FaultyIo::new(&bytes[..], 0).truncate_after(bytes_read);
+        let result = Packet::deserialize(&mut io).await;
+
+        assert!(
+            matches!(&result, Err(Error::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof),
+            "truncated after {} bytes: {:?}",
+            bytes_read,
+            result.map(|packet| packet.get_id())
+        );
+    }
+}
+
+#[tokio::test]
+async fn deserialize_corrupted_packets_does_not_panic() {
+    let bytes = bytes();
+
+    for seed in 0..SEEDS {
+        let mut io = FaultyIo::new(&bytes[..], seed).split_reads().corrupt(0.05);
+
+        while Packet::deserialize(&mut io).await.is_ok() {}
+    }
+}
+
+#[tokio::test]
+async fn faults_are_reproducible() {
+    let bytes = vec![0u8; 1000];
+
+    let read_sizes = |seed| {
+        let mut io = FaultyIo::new(&bytes[..], seed).split_reads();
+        let mut sizes = Vec::new();
+
+        async move {
+            let mut buf = [0u8; 100];
+
+            loop {
+                match io.read(&mut buf).await.unwrap() {
+                    0 => return sizes,
+                    read => sizes.push(read),
+                }
+            }
+        }
+    };
+
+    assert_eq!(read_sizes(7).await, read_sizes(7).await);
+    assert_ne!(read_sizes(7).await, read_sizes(8).await);
+}
+
+#[tokio::test(start_paused = true)]
+async fn multi_packet_responses_survive_split_reads() {
+    let response = "Zoë 🦀 ".repeat(2000);
+
+    for flavor in [Flavor::Generic, Flavor::Minecraft] {
+        for seed in 0..SEEDS {
+            let (io, handle) = MockServer::new("hunter2")
+                .flavor(flavor)
+                .expect("help", Reply::text(response.clone()))
+                .expect("list", Reply::text("There are 0 players"))
+                .spawn();
+            let io = FaultyIo::new(io, seed)
+                .split_reads()
+                .latency(Duration::from_millis(5));
+            let mut conn = <Connection<FaultyIo<DuplexStream>>>::builder()
+                .sleep_fn(tokio::time::sleep)
+                .handshake(io, "hunter2")
+                .await
+                .unwrap();
+
+            assert_eq!(conn.cmd("help").await.unwrap(), response, "seed {}", seed);
+            assert_eq!(conn.cmd("list").await.unwrap(), "There are 0 players");
+            handle.assert_done();
+        }
+    }
+}
+
+#[tokio::test]
+async fn connection_reset_mid_response() {
+    let (io, _) = MockServer::new("hunter2")
+        .expect("help", Reply::text("x".repeat(10_000)))
+        .spawn();
+    // the auth response is 14 bytes long
+    let io = FaultyIo::new(io, 0).reset_after(14 + 5000);
+    let mut conn = <Connection<FaultyIo<DuplexStream>>>::builder()
+        .handshake(io, "hunter2")
+        .await
+        .unwrap();
+
+    let result = conn.cmd("help").await;
+
+    assert!(matches!(result, Err(Error::Io(err)) if err.kind() == io::ErrorKind::ConnectionReset));
+}
+
+#[tokio::test(start_paused = true)]
+async fn stalled_writes_time_out() {
+    let (io, _) = MockServer::new("hunter2").spawn();
+    let io = FaultyIo::new(io, 0).stall_writes(Duration::from_secs(10));
+    let result = <Connection<FaultyIo<DuplexStream>>>::builder()
+        .auth_timeout(Duration::from_secs(5))
+        .sleep_fn(tokio::time::sleep)
+        .handshake(io, "hunter2")
+        .await;
+
+    assert!(matches!(result, Err(Error::Timeout)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn stalled_command_writes_time_out() {
+    let (io, _) = MockServer::new("hunter2")
+        .respond("list", Reply::text("There are 0 players"))
+        .spawn();
+    let io = FaultyIo::new(io, 0).stall_writes(Duration::from_secs(10));
+    let mut conn = <Connection<FaultyIo<DuplexStream>>>::builder()
+        .command_timeout(Duration::from_secs(5))
+        .sleep_fn(tokio::time::sleep)
+        .handshake(io, "hunter2")
+        .await
+        .unwrap();
+
+    assert!(matches!(conn.cmd("list").await, Err(Error::Timeout)));
+    // part of a packet may have been written, so the connection cannot be used anymore
+    assert!(matches!(conn.cmd("list").await, Err(Error::Closed)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn cancelled_command_writes_break_the_connection() {
+    let (io, _) = MockServer::new("hunter2")
+        .respond("list", Reply::text("There are 0 players"))
+        .spawn();
+    let io = FaultyIo::new(io, 0).stall_writes(Duration::from_secs(10));
+    let mut conn = <Connection<FaultyIo<DuplexStream>>>::builder()
+        .sleep_fn(tokio::time::sleep)
+        .handshake(io, "hunter2")
+        .await
+        .unwrap();
+
+    // the caller gives up while the command is being written
+    let cancelled = tokio::time::timeout(Duration::from_secs(1), conn.cmd("list")).await;
+    assert!(cancelled.is_err());
+    assert!(matches!(conn.cmd("list").await, Err(Error::Closed)));
+}
@@ -0,0 +1,137 @@
+use rcon::formatting::{strip_control_chars, Color, FormattedText, Span, Style};
+
+fn span(text: &str, style: Style) -> Span {
+    Span {
+        text: text.to_owned(),
+        style,
+    }
+}
+
+#[test]
+fn codes_are_parsed_into_spans() {
+    let text = FormattedText::parse("§6§lGold §ois §Rplain§kx§mx§ny");
+
+    let bold = Style {
+        color: Some(Color::Gold),
+        bold: true,
+        ..Style::default()
+    };
+    let obfuscated = Style {
+        obfuscated: true,
+        ..Style::default()
+    };
+    assert_eq!(
+        text.spans(),
+        [
+            span("Gold ", bold),
+            span(
+                "is ",
+                Style {
+                    italic: true,
+                    ..bold
+                }
+            ),
+            span("plain", Style::default()),
+            span("x", obfuscated),
+            span(
+                "x",
+                Style {
+                    strikethrough: true,
+                    ..obfuscated
+                }
+            ),
+            span(
+                "y",
+                Style {
+                    strikethrough: true,
+                    underlined: true,
+                    ..obfuscated
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn color_codes_reset_the_formatting() {
+    let text = FormattedText::parse("§l§abold? §cred§c§r");
+
+    assert_eq!(
+        text.spans(),
+        [
+            span(
+                "bold? ",
+                Style {
+                    color: Some(Color::Green),
+                    ..Style::default()
+                }
+            ),
+            span(
+                "red",
+                Style {
+                    color: Some(Color::Red),
+                    ..Style::default()
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn hex_colors_are_parsed() {
+    // the second hex color is incomplete, leaving the codes of its digits
+    let text = FormattedText::parse("§x§F§F§a§a§0§1orange §x§1§2§3nope");
+
+    assert_eq!(
+        text.spans(),
+        [
+            span(
+                "orange ",
+                Style {
+                    color: Some(Color::Rgb(0xff, 0xaa, 0x01)),
+                    ..Style::default()
+                }
+            ),
+            span(
+                "nope",
+                Style {
+                    color: Some(Color::DarkAqua),
+                    ..Style::default()
+                }
+            ),
+        ]
+    );
+    assert_eq!(
+        text.to_ansi(),
+        "\x1b[38;2;255;170;1morange \x1b[0m\x1b[36mnope\x1b[0m"
+    );
+}
+
+#[test]
+fn control_characters_are_not_passed_to_terminals() {
+    let text = FormattedText::parse("\x1b]0;pwned\x07a\tb\r\n§c\x1b[2Jc\u{9b}d\x7f");
+
+    // without the escape character, the rest of a sequence is harmless text
+    assert_eq!(text.to_ansi(), "]0;pwneda\tb\n\x1b[91m[2Jcd\x1b[0m");
+    assert_eq!(strip_control_chars("\x08x\ny\0"), "x\ny");
+}
+
+#[test]
+fn unknown_and_incomplete_codes_are_removed() {
+    for (text, plain) in [("§zab", "ab"), ("a§zb§", "ab"), ("§", ""), ("a§x§fb", "ab")] {
+        assert_eq!(FormattedText::parse(text).to_plain(), plain, "{}", text);
+    }
+}
+
+#[test]
+fn html_is_escaped_and_styled() {
+    let text = FormattedText::parse("<b>§c§l§n§mR&D§r §kx §9\"'");
+
+    assert_eq!(
+        text.to_html(),
+        "&lt;b&gt;<span style=\"color:#ff5555;font-weight:bold;\
+         text-decoration:underline line-through\">R&amp;D</span> \
+         <span class=\"obfuscated\">x </span>\
+         <span style=\"color:#5555ff\">&quot;&#39;</span>"
+    );
+}
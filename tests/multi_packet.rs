@@ -0,0 +1,55 @@
+use rcon::{ClientSession, Connection, Event, PacketType};
+use tokio::io::DuplexStream;
+
+mod common;
+
+use common::encode;
+
+const RESPONSE: &str = "There are 3 of a max of 20 players online: Jürgen, 李雷, Zoë 🦀";
+
+#[test]
+fn session_reassembles_response_split_at_every_offset() {
+    let response = RESPONSE.as_bytes();
+
+    for offset in 0..=response.len() {
+        let mut session = ClientSession::new();
+        let id = session.command("list");
+        let end_id = id + 1;
+
+        session
+            .receive(&encode(id, PacketType::ResponseValue, &response[..offset]))
+            .unwrap();
+        session
+            .receive(&encode(id, PacketType::ResponseValue, &response[offset..]))
+            .unwrap();
+        session
+            .receive(&encode(end_id, PacketType::ResponseValue, b""))
+            .unwrap();
+
+        assert_eq!(
+            session.poll_event(),
+            Some(Event::Response {
+                id,
+                body: response.to_vec()
+            }),
+            "split at offset {}",
+            offset
+        );
+        assert_eq!(session.last_response_packets(), 3);
+    }
+}
+
+#[tokio::test]
+async fn cmd_decodes_multibyte_characters_split_across_packets() {
+    for offset in 0..=RESPONSE.len() {
+        let (first, second) = RESPONSE.as_bytes().split_at(offset);
+        let io = common::spawn_split(vec![first.to_vec(), second.to_vec()]);
+
+        let mut conn = <Connection<DuplexStream>>::builder()
+            .handshake(io, "hunter2")
+            .await
+            .unwrap();
+
+        assert_eq!(conn.cmd("list").await.unwrap(), RESPONSE);
+    }
+}
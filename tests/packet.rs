@@ -0,0 +1,93 @@
+use rcon::{Error, Packet, PacketDecoder, PacketType};
+
+fn raw_packet(length: i32, id: i32, ptype: i32, rest: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&length.to_le_bytes());
+    buf.extend_from_slice(&id.to_le_bytes());
+    buf.extend_from_slice(&ptype.to_le_bytes());
+    buf.extend_from_slice(rest);
+    buf
+}
+
+#[test]
+fn decoder_rejects_negative_length() {
+    let mut decoder = PacketDecoder::new();
+    decoder.feed(&raw_packet(-1, 1, 0, b"\0\0"));
+
+    assert!(matches!(decoder.decode(), Err(Error::MalformedPacket(_))));
+}
+
+#[test]
+fn decoder_rejects_length_below_minimum() {
+    let mut decoder = PacketDecoder::new();
+    decoder.feed(&raw_packet(9, 1, 0, b"\0\0"));
+
+    assert!(matches!(decoder.decode(), Err(Error::MalformedPacket(_))));
+}
+
+#[test]
+fn decoder_rejects_oversized_packet_before_body_arrives() {
+    let mut decoder = PacketDecoder::new().max_packet_size(4106);
+    decoder.feed(&4107i32.to_le_bytes());
+
+    assert!(matches!(
+        decoder.decode(),
+        Err(Error::PacketTooLarge {
+            size: 4107,
+            max: 4106
+        })
+    ));
+}
+
+#[test]
+fn decoder_rejects_missing_terminator() {
+    let mut decoder = PacketDecoder::new();
+    decoder.feed(&raw_packet(12, 1, 0, b"abcd"));
+
+    assert!(matches!(decoder.decode(), Err(Error::MalformedPacket(_))));
+}
+
+#[test]
+fn decoder_waits_for_complete_packet() {
+    let bytes = raw_packet(14, 7, 0, b"pong\0\0");
+    let mut decoder = PacketDecoder::new();
+
+    decoder.feed(&bytes[..bytes.len() - 1]);
+    assert!(decoder.decode().unwrap().is_none());
+
+    decoder.feed(&bytes[bytes.len() - 1..]);
+    let packet = decoder.decode().unwrap().unwrap();
+    assert_eq!(packet.get_id(), 7);
+    assert_eq!(packet.get_type(), PacketType::ResponseValue);
+    assert_eq!(packet.get_body(), b"pong");
+}
+
+#[tokio::test]
+async fn deserialize_rejects_oversized_packet() {
+    let bytes = raw_packet(i32::MAX, 1, 0, b"");
+    let result = Packet::deserialize_with_max_size(&mut &bytes[..], 4096).await;
+
+    assert!(matches!(result, Err(Error::PacketTooLarge { .. })));
+}
+
+#[tokio::test]
+async fn deserialize_rejects_negative_length() {
+    let bytes = raw_packet(-100, 1, 0, b"");
+    let result = Packet::deserialize(&mut &bytes[..]).await;
+
+    assert!(matches!(result, Err(Error::MalformedPacket(_))));
+}
+
+#[test]
+fn bodies_up_to_the_length_field_are_accepted() {
+    // zeroed allocations are not touched, so this does not need 2 GiB of memory
+    let packet = Packet::new(1, PacketType::ExecCommand, vec![0; i32::MAX as usize - 10]);
+
+    assert_eq!(packet.get_body().len(), i32::MAX as usize - 10);
+}
+
+#[test]
+#[should_panic(expected = "smaller than 2 GiB")]
+fn bodies_overflowing_the_length_field_are_rejected() {
+    let _ = Packet::new(1, PacketType::ExecCommand, vec![0; i32::MAX as usize - 9]);
+}
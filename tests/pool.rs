@@ -0,0 +1,274 @@
+use std::future::Ready;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rcon::{Connection, PacketType, Pool, PoolConfig, SinglePacket};
+use tokio::io::DuplexStream;
+
+mod common;
+
+/// A stand-in server that echoes `commands` commands and hangs up on the next one.
+/// `live` tracks the number of open connections.
+async fn echo(io: DuplexStream, commands: usize, live: Arc<AtomicUsize>) {
+    let mut answered = 0;
+
+    common::serve(io, |request| {
+        if request.get_type() != PacketType::Auth {
+            if answered == commands {
+                return None;
+            }
+            answered += 1;
+        }
+
+        Some(vec![common::echo(&request)])
+    })
+    .await;
+
+    live.fetch_sub(1, Ordering::SeqCst);
+}
+
+struct Server {
+    connects: Arc<AtomicUsize>,
+    live: Arc<AtomicUsize>,
+    max_live: Arc<AtomicUsize>,
+}
+
+impl Server {
+    fn new() -> Self {
+        Self {
+            connects: Arc::new(AtomicUsize::new(0)),
+            live: Arc::new(AtomicUsize::new(0)),
+            max_live: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn pool(&self, commands: usize, config: PoolConfig) -> Pool<DuplexStream> {
+        let connects = self.connects.clone();
+        let live = self.live.clone();
+        let max_live = self.max_live.clone();
+        let connect = move || -> Ready<io::Result<DuplexStream>> {
+            connects.fetch_add(1, Ordering::SeqCst);
+            let now_live = live.fetch_add(1, Ordering::SeqCst) + 1;
+            max_live.fetch_max(now_live, Ordering::SeqCst);
+
+            let (client, server) = tokio::io::duplex(4096);
+            tokio::spawn(echo(server, commands, live.clone()));
+            std::future::ready(Ok(client))
+        };
+
+        <Connection<DuplexStream>>::builder()
+            .response_terminator(SinglePacket)
+            .pool_with(connect, "hunter2", config)
+    }
+
+    fn connects(&self) -> usize {
+        self.connects.load(Ordering::SeqCst)
+    }
+}
+
+#[tokio::test]
+async fn connections_are_reused() {
+    let server = Server::new();
+    let pool = server.pool(usize::MAX, PoolConfig::default());
+
+    for _ in 0..3 {
+        let mut conn = pool.get().await.unwrap();
+        assert_eq!(conn.cmd("list").await.unwrap(), "list");
+    }
+
+    assert_eq!(server.connects(), 1);
+    assert_eq!(pool.open(), 1);
+    assert_eq!(pool.idle(), 1);
+}
+
+#[tokio::test]
+async fn open_connections_are_capped() {
+    let server = Server::new();
+    let config = PoolConfig {
+        max_size: 2,
+        ..PoolConfig::default()
+    };
+    let pool = server.pool(usize::MAX, config);
+
+    let tasks: Vec<_> = (0..8)
+        .map(|i| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let mut conn = pool.get().await.unwrap();
+                tokio::task::yield_now().await;
+                conn.cmd(&format!("say {}", i)).await.unwrap()
+            })
+        })
+        .collect();
+
+    for (i, task) in tasks.into_iter().enumerate() {
+        assert_eq!(task.await.unwrap(), format!("say {}", i));
+    }
+
+    assert!(server.max_live.load(Ordering::SeqCst) <= 2);
+    assert!(pool.open() <= 2);
+}
+
+#[tokio::test]
+async fn unhealthy_connections_are_replaced() {
+    let server = Server::new();
+    let config = PoolConfig {
+        health_check: Some("ping".to_owned()),
+        ..PoolConfig::default()
+    };
+    // the server hangs up after one command, so the health check fails
+    let pool = server.pool(1, config);
+
+    for _ in 0..2 {
+        let mut conn = pool.get().await.unwrap();
+        assert_eq!(conn.cmd("list").await.unwrap(), "list");
+    }
+
+    assert_eq!(server.connects(), 2);
+    assert_eq!(pool.open(), 1);
+}
+
+#[tokio::test]
+async fn expired_connections_are_closed() {
+    let server = Server::new();
+    let config = PoolConfig {
+        max_lifetime: Some(Duration::ZERO),
+        ..PoolConfig::default()
+    };
+    let pool = server.pool(usize::MAX, config);
+
+    drop(pool.get().await.unwrap());
+    drop(pool.get().await.unwrap());
+
+    assert_eq!(server.connects(), 2);
+    assert_eq!(pool.open(), 1);
+}
+
+#[tokio::test]
+async fn idle_eviction_keeps_min_size() {
+    let server = Server::new();
+    let config = PoolConfig {
+        min_size: 2,
+        idle_timeout: Some(Duration::ZERO),
+        ..PoolConfig::default()
+    };
+    let pool = server.pool(usize::MAX, config);
+
+    pool.warm_up().await.unwrap();
+    assert_eq!(pool.open(), 2);
+    assert_eq!(pool.idle(), 2);
+
+    // idle for too long, but closing it would leave fewer than `min_size` connections
+    let mut conn = pool.get().await.unwrap();
+    assert_eq!(conn.cmd("list").await.unwrap(), "list");
+    assert_eq!(server.connects(), 2);
+
+    conn.discard();
+    assert_eq!(pool.open(), 1);
+}
+
+#[tokio::test]
+async fn all_expired_connections_are_closed() {
+    let server = Server::new();
+    let config = PoolConfig {
+        idle_timeout: Some(Duration::from_millis(50)),
+        ..PoolConfig::default()
+    };
+    let pool = server.pool(usize::MAX, config);
+
+    let (first, second) = tokio::join!(pool.get(), pool.get());
+    drop(first.unwrap());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    drop(second.unwrap());
+
+    // the most recently used connection is reused, the expired one is closed
+    let _conn = pool.get().await.unwrap();
+    assert_eq!(server.connects(), 2);
+    assert_eq!(pool.open(), 1);
+    assert_eq!(pool.idle(), 0);
+}
+
+#[tokio::test]
+async fn warm_up_respects_max_size() {
+    let server = Server::new();
+    let config = PoolConfig {
+        min_size: 2,
+        max_size: 2,
+        ..PoolConfig::default()
+    };
+    let pool = server.pool(usize::MAX, config);
+
+    let (warm_up, first, second) = tokio::join!(pool.warm_up(), pool.get(), pool.get());
+    warm_up.unwrap();
+    let _conns = (first.unwrap(), second.unwrap());
+
+    assert_eq!(server.max_live.load(Ordering::SeqCst), 2);
+    assert_eq!(pool.open(), 2);
+}
+
+#[tokio::test]
+async fn failed_and_cancelled_commands_close_the_connection() {
+    let server = Server::new();
+    let pool = server.pool(1, PoolConfig::default());
+
+    // the server hangs up after one command
+    let mut conn = pool.get().await.unwrap();
+    assert_eq!(conn.cmd("list").await.unwrap(), "list");
+    assert!(conn.cmd("list").await.is_err());
+    drop(conn);
+    assert_eq!(pool.open(), 0);
+
+    let mut conn = pool.get().await.unwrap();
+    // give up on the command after it has been sent
+    tokio::select! {
+        biased;
+        _ = conn.cmd("list") => panic!("the command completed without a response"),
+        _ = std::future::ready(()) => {}
+    }
+    drop(conn);
+    assert_eq!(pool.open(), 0);
+    assert_eq!(pool.idle(), 0);
+}
+
+#[tokio::test]
+async fn cancelled_health_checks_close_the_connection() {
+    let server = Server::new();
+    let config = PoolConfig {
+        health_check: Some("ping".to_owned()),
+        ..PoolConfig::default()
+    };
+    let pool = server.pool(usize::MAX, config);
+    drop(pool.get().await.unwrap());
+    assert_eq!(pool.open(), 1);
+
+    // give up on checking out while the health check waits for its response
+    tokio::select! {
+        biased;
+        _ = pool.get() => panic!("the health check completed without a response"),
+        _ = std::future::ready(()) => {}
+    }
+    assert_eq!(pool.open(), 0);
+    assert_eq!(pool.idle(), 0);
+
+    let mut conn = pool.get().await.unwrap();
+    assert_eq!(conn.cmd("list").await.unwrap(), "list");
+    assert_eq!(server.connects(), 2);
+}
+
+#[test]
+#[should_panic(expected = "must not exceed its maximum size")]
+fn min_size_must_not_exceed_max_size() {
+    let config = PoolConfig {
+        min_size: 5,
+        max_size: 4,
+        ..PoolConfig::default()
+    };
+
+    let _ = <Connection<DuplexStream>>::builder().pool_with(
+        std::future::pending::<io::Result<DuplexStream>>,
+        "hunter2",
+        config,
+    );
+}
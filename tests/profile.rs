@@ -0,0 +1,131 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rcon::testing::{Flavor, MockServer, Reply};
+use rcon::{
+    Builder, Charset, Connection, Error, PacketType, ProfileSettings, ServerProfile, SinglePacket,
+    MINECRAFT_MAX_PAYLOAD_SIZE,
+};
+use tokio::io::DuplexStream;
+
+async fn handshake(builder: Builder<DuplexStream>, mock: MockServer) -> Connection<DuplexStream> {
+    let (io, _) = mock.spawn();
+
+    builder
+        .sleep_fn(tokio::time::sleep)
+        .handshake(io, "hunter2")
+        .await
+        .unwrap()
+}
+
+fn mock(flavor: Flavor) -> MockServer {
+    MockServer::new("hunter2").flavor(flavor)
+}
+
+fn end_marker(profile: ServerProfile) -> Option<(PacketType, Vec<u8>)> {
+    profile
+        .settings()
+        .response_terminator
+        .end_marker()
+        .map(|(ptype, body)| (ptype, body.to_vec()))
+}
+
+#[test]
+fn presets_bundle_their_quirks() {
+    let generic = ServerProfile::Generic.settings();
+    assert_eq!(generic.max_command_len, None);
+    assert_eq!(generic.command_delay, None);
+    assert_eq!(generic.charset, Charset::Utf8);
+    assert_eq!(
+        end_marker(ServerProfile::Generic),
+        Some((PacketType::ExecCommand, Vec::new()))
+    );
+
+    let minecraft = ServerProfile::Minecraft.settings();
+    assert_eq!(minecraft.max_command_len, Some(MINECRAFT_MAX_PAYLOAD_SIZE));
+    assert_eq!(minecraft.command_delay, Some(Duration::from_millis(3)));
+
+    for profile in [ServerProfile::Source, ServerProfile::Squad] {
+        assert_eq!(
+            end_marker(profile),
+            Some((PacketType::ResponseValue, Vec::new()))
+        );
+    }
+    assert_eq!(
+        end_marker(ServerProfile::Factorio),
+        Some((PacketType::ExecCommand, b"/version".to_vec()))
+    );
+    assert_eq!(end_marker(ServerProfile::Ark), None);
+    assert_eq!(end_marker(ServerProfile::Palworld), None);
+    assert_eq!(
+        ServerProfile::Palworld.settings().charset,
+        Charset::Utf8Lossy
+    );
+}
+
+#[tokio::test]
+async fn custom_settings_apply_to_connections() {
+    let settings = ProfileSettings {
+        max_command_len: Some(4),
+        command_delay: Some(Duration::from_millis(1)),
+        response_terminator: Arc::new(SinglePacket),
+        charset: Charset::Latin1,
+    };
+    // the mock decodes commands as UTF-8, replacing the Latin-1 `é`
+    let (io, handle) = mock(Flavor::Generic)
+        .expect("caf\u{fffd}", Reply::bytes(&b"caf\xe9"[..]))
+        .spawn();
+    let mut conn = Connection::builder()
+        .profile(ServerProfile::Custom(settings))
+        .sleep_fn(tokio::time::sleep)
+        .handshake(io, "hunter2")
+        .await
+        .unwrap();
+
+    assert!(matches!(conn.profile(), ServerProfile::Custom(_)));
+    assert!(matches!(
+        conn.cmd("hello").await,
+        Err(Error::CommandTooLong)
+    ));
+    // a single packet without an end marker, encoded as Latin-1
+    assert_eq!(conn.cmd("café").await.unwrap(), "café");
+    handle.assert_done();
+}
+
+#[tokio::test]
+async fn quirk_toggles_select_profiles() {
+    let conn = handshake(
+        Connection::builder().enable_minecraft_quirks(true),
+        mock(Flavor::Minecraft),
+    )
+    .await;
+    assert!(matches!(conn.profile(), ServerProfile::Minecraft));
+
+    let conn = handshake(
+        Connection::builder()
+            .enable_minecraft_quirks(true)
+            .enable_factorio_quirks(true),
+        mock(Flavor::Factorio),
+    )
+    .await;
+    assert!(matches!(conn.profile(), ServerProfile::Factorio));
+
+    // disabling other quirks keeps the selected profile
+    let conn = handshake(
+        Connection::builder()
+            .profile(ServerProfile::Minecraft)
+            .enable_factorio_quirks(false),
+        mock(Flavor::Minecraft),
+    )
+    .await;
+    assert!(matches!(conn.profile(), ServerProfile::Minecraft));
+
+    let conn = handshake(
+        Connection::builder()
+            .enable_factorio_quirks(true)
+            .enable_factorio_quirks(false),
+        mock(Flavor::Generic),
+    )
+    .await;
+    assert!(matches!(conn.profile(), ServerProfile::Generic));
+}
@@ -0,0 +1,151 @@
+use std::io::{BufRead, BufReader};
+use std::net::SocketAddr;
+use std::process::{Child, Command, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use rcon::testing::{Flavor, MockHandle, MockServer, Reply};
+use rcon::{Connection, Error};
+use tokio::net::TcpStream;
+
+/// Kills the proxy when the test ends.
+struct Proxy {
+    child: Child,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl Proxy {
+    /// Wait for a line containing `text` to be logged, and return it.
+    async fn logged(&self, text: &str) -> String {
+        for _ in 0..100 {
+            let log = self.log.lock().unwrap().clone();
+
+            if let Some(line) = log.into_iter().find(|line| line.contains(text)) {
+                return line;
+            }
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        panic!("{:?} was not logged", text);
+    }
+}
+
+impl Drop for Proxy {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+async fn start() -> (Proxy, SocketAddr, MockHandle) {
+    let (upstream, handle) = MockServer::new("hunter2")
+        .flavor(Flavor::Minecraft)
+        .respond("list", Reply::text("There are 0 players"))
+        .respond("seed", Reply::text("Seed: [42]"))
+        .respond("help", Reply::text("x".repeat(10_000)))
+        .listen()
+        .await
+        .unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rcon-proxy"))
+        .args(["--listen", "127.0.0.1:0", "--profile", "minecraft"])
+        .args(["--upstream", &upstream.to_string()])
+        .args(["--password", "alice", "--password", "bob"])
+        .env("RCON_UPSTREAM_PASSWORD", "hunter2")
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let proxy = Proxy {
+        child,
+        log: log.clone(),
+    };
+
+    // the proxy announces its address once it accepts clients, and its log must be
+    // drained for as long as it runs
+    let (address_tx, address_rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            if let Some((_, address)) = line.split_once("listening on ") {
+                let _ = address_tx.send(address.parse::<SocketAddr>().unwrap());
+            }
+            log.lock().unwrap().push(line);
+        }
+    });
+    let address = tokio::task::spawn_blocking(move || address_rx.recv().expect("the proxy exited"))
+        .await
+        .unwrap();
+
+    (proxy, address, handle)
+}
+
+async fn connect(address: SocketAddr, password: &str) -> rcon::Result<Connection<TcpStream>> {
+    <Connection<TcpStream>>::builder()
+        .connect(address, password)
+        .await
+}
+
+#[tokio::test]
+async fn clients_share_the_upstream_connection() {
+    let (_proxy, address, handle) = start().await;
+
+    let clients = ["alice", "bob", "alice"].iter().map(|password| async move {
+        let mut conn = connect(address, password).await.unwrap();
+
+        for _ in 0..5 {
+            assert_eq!(conn.cmd("list").await.unwrap(), "There are 0 players");
+            assert_eq!(conn.cmd("help").await.unwrap(), "x".repeat(10_000));
+            assert_eq!(conn.cmd("seed").await.unwrap(), "Seed: [42]");
+        }
+    });
+    let clients: Vec<_> = clients.map(tokio::spawn).collect();
+
+    for client in clients {
+        client.await.unwrap();
+    }
+
+    assert_eq!(handle.received().len(), 3 * 5 * 3);
+    handle.assert_done();
+}
+
+#[tokio::test]
+async fn clients_use_their_own_passwords() {
+    let (_proxy, address, _) = start().await;
+
+    assert!(matches!(
+        connect(address, "hunter2").await,
+        Err(Error::Auth)
+    ));
+    assert!(connect(address, "bob").await.is_ok());
+}
+
+#[tokio::test]
+async fn upstream_quirks_are_enforced() {
+    let (proxy, address, handle) = start().await;
+    let mut conn = connect(address, "alice").await.unwrap();
+
+    // failures are not mistaken for replies
+    assert!(conn.cmd(&"x".repeat(2000)).await.is_err());
+    assert!(handle.received().is_empty());
+
+    // commands may contain secrets, so only the client and the error are logged
+    let line = proxy.logged("failed, disconnecting").await;
+    assert!(line.contains("127.0.0.1"), "{}", line);
+    assert!(
+        line.contains("command exceeds the maximum length"),
+        "{}",
+        line
+    );
+    assert!(!proxy
+        .log
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|line| line.contains("xxx")));
+
+    let mut conn = connect(address, "alice").await.unwrap();
+    assert_eq!(conn.cmd("list").await.unwrap(), "There are 0 players");
+}
@@ -0,0 +1,150 @@
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rcon::{Backoff, Connection, Error, PacketType, SinglePacket};
+use tokio::io::DuplexStream;
+
+mod common;
+
+/// A stand-in server that echoes `commands` commands and drops the connection on
+/// the next one, like a Source server changing maps.
+fn flaky(commands: usize) -> DuplexStream {
+    let mut answered = 0;
+
+    common::spawn(move |request| {
+        if request.get_type() != PacketType::Auth {
+            if answered == commands {
+                return None;
+            }
+            answered += 1;
+        }
+
+        Some(vec![common::echo(&request)])
+    })
+}
+
+/// Count connection attempts, refusing the first `refused` of them.
+fn connector(
+    attempts: Arc<AtomicUsize>,
+    refused: usize,
+    commands: usize,
+) -> impl Fn() -> std::future::Ready<io::Result<DuplexStream>> + Send + Sync + 'static {
+    move || {
+        if attempts.fetch_add(1, Ordering::SeqCst) < refused {
+            return std::future::ready(Err(io::ErrorKind::ConnectionRefused.into()));
+        }
+
+        std::future::ready(Ok(flaky(commands)))
+    }
+}
+
+fn counter() -> (Arc<AtomicUsize>, impl Fn() + Send + Sync + 'static) {
+    let count = Arc::new(AtomicUsize::new(0));
+    let increment = {
+        let count = count.clone();
+        move || {
+            count.fetch_add(1, Ordering::SeqCst);
+        }
+    };
+
+    (count, increment)
+}
+
+fn builder() -> rcon::Builder<DuplexStream> {
+    <Connection<DuplexStream>>::builder()
+        .response_terminator(SinglePacket)
+        .sleep_fn(tokio::time::sleep)
+}
+
+#[tokio::test(start_paused = true)]
+async fn reconnects_after_the_connection_broke() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let (connected, on_connected) = counter();
+    let (reauthenticated, on_reauthenticated) = counter();
+    let (disconnected, on_disconnected) = counter();
+
+    let mut conn = builder()
+        .reconnect_with(connector(attempts.clone(), 0, 1), "hunter2")
+        .on_connected(on_connected)
+        .on_reauthenticated(on_reauthenticated)
+        .on_disconnected(move |_| on_disconnected());
+
+    assert_eq!(conn.cmd("first").await.unwrap(), "first");
+    // the server hung up after the first command
+    assert!(matches!(conn.cmd("second").await, Err(Error::Io(_))));
+    assert!(!conn.is_connected());
+    assert_eq!(conn.cmd("third").await.unwrap(), "third");
+
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    assert_eq!(connected.load(Ordering::SeqCst), 2);
+    assert_eq!(reauthenticated.load(Ordering::SeqCst), 1);
+    assert_eq!(disconnected.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn idempotent_commands_are_retried() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let mut conn = builder().reconnect_with(connector(attempts.clone(), 0, 1), "hunter2");
+
+    assert_eq!(conn.cmd_idempotent("list").await.unwrap(), "list");
+    assert_eq!(conn.cmd_idempotent("list").await.unwrap(), "list");
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn backoff_between_attempts() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let backoff = Backoff {
+        initial: Duration::from_secs(1),
+        jitter: 0.0,
+        ..Backoff::default()
+    };
+    let mut conn = builder()
+        .reconnect_with(connector(attempts.clone(), 3, 1), "hunter2")
+        .backoff(backoff);
+
+    let start = tokio::time::Instant::now();
+    assert_eq!(conn.cmd("list").await.unwrap(), "list");
+
+    // 1s + 2s + 4s
+    assert_eq!(start.elapsed(), Duration::from_secs(7));
+    assert_eq!(attempts.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test(start_paused = true)]
+async fn gives_up_after_max_attempts() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let backoff = Backoff {
+        max_attempts: Some(2),
+        ..Backoff::default()
+    };
+    let mut conn = builder()
+        .reconnect_with(connector(attempts.clone(), usize::MAX, 1), "hunter2")
+        .backoff(backoff);
+
+    assert!(matches!(conn.cmd("list").await, Err(Error::Io(_))));
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn backoff_delay_saturates_at_max() {
+    let backoff = Backoff::default();
+
+    assert_eq!(backoff.delay(1), Duration::from_millis(100));
+    assert_eq!(backoff.delay(3), Duration::from_millis(400));
+    assert_eq!(backoff.delay(100), backoff.max);
+    assert_eq!(backoff.delay(u32::MAX), backoff.max);
+}
+
+#[test]
+#[should_panic(expected = "multiplier")]
+fn backoff_rejects_shrinking_delays() {
+    let _ = builder()
+        .reconnect_with(connector(Arc::default(), 0, 1), "hunter2")
+        .backoff(Backoff {
+            multiplier: f64::NAN,
+            ..Backoff::default()
+        });
+}
@@ -0,0 +1,202 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rcon::server::{Accept, Listener, Server};
+use rcon::{Connection, Error, Packet, PacketType, SourceMirror};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+fn server() -> Server {
+    Server::new("hunter2", |cmd: String| async move {
+        match cmd.as_str() {
+            "long" => "x".repeat(10_000),
+            "silent" => String::new(),
+            _ => format!("echo: {}", cmd),
+        }
+    })
+}
+
+fn serve() -> DuplexStream {
+    let (client, server_io) = tokio::io::duplex(4096);
+    tokio::spawn(async move { server().serve_connection(server_io).await });
+    client
+}
+
+#[tokio::test]
+async fn commands_are_handled() {
+    let mut conn = <Connection<DuplexStream>>::builder()
+        .handshake(serve(), "hunter2")
+        .await
+        .unwrap();
+
+    assert_eq!(conn.cmd("list").await.unwrap(), "echo: list");
+    assert_eq!(conn.cmd("silent").await.unwrap(), "");
+}
+
+#[tokio::test]
+async fn long_replies_are_split() {
+    for builder in [
+        <Connection<DuplexStream>>::builder(),
+        <Connection<DuplexStream>>::builder().response_terminator(SourceMirror),
+    ] {
+        let mut conn = builder.handshake(serve(), "hunter2").await.unwrap();
+
+        assert_eq!(conn.cmd("long").await.unwrap(), "x".repeat(10_000));
+        assert_eq!(conn.cmd("list").await.unwrap(), "echo: list");
+    }
+}
+
+#[tokio::test]
+async fn wrong_password_is_rejected() {
+    let result = <Connection<DuplexStream>>::builder()
+        .handshake(serve(), "hunter3")
+        .await;
+
+    assert!(matches!(result, Err(Error::Auth)));
+}
+
+#[tokio::test]
+async fn repeated_auth_failures_disconnect() {
+    let (mut client, server_io) = tokio::io::duplex(4096);
+    tokio::spawn(async move {
+        server()
+            .max_auth_failures(2)
+            .serve_connection(server_io)
+            .await
+    });
+
+    let mut out = Vec::new();
+    for id in 1..=2 {
+        Packet::new(id, PacketType::Auth, b"hunter3".to_vec()).encode(&mut out);
+    }
+    client.write_all(&out).await.unwrap();
+
+    // two failed auth responses of 14 bytes each, then the end of the stream
+    let mut replies = Vec::new();
+    client.read_to_end(&mut replies).await.unwrap();
+    assert_eq!(replies.len(), 28);
+}
+
+#[tokio::test]
+async fn auth_failures_can_be_unlimited() {
+    let (mut client, server_io) = tokio::io::duplex(4096);
+    tokio::spawn(async move {
+        server()
+            .max_auth_failures(0)
+            .serve_connection(server_io)
+            .await
+    });
+
+    let mut out = Vec::new();
+    for id in 1..=5 {
+        Packet::new(id, PacketType::Auth, b"hunter3".to_vec()).encode(&mut out);
+    }
+    client.write_all(&out).await.unwrap();
+    let mut replies = [0; 5 * 14];
+    client.read_exact(&mut replies).await.unwrap();
+
+    let mut conn = <Connection<DuplexStream>>::builder()
+        .handshake(client, "hunter2")
+        .await
+        .unwrap();
+    assert_eq!(conn.cmd("list").await.unwrap(), "echo: list");
+    assert_eq!(conn.cmd("seed").await.unwrap(), "echo: seed");
+}
+
+#[tokio::test]
+async fn failed_commands_disconnect() {
+    let (client, server_io) = tokio::io::duplex(4096);
+    let server = Server::new("hunter2", |cmd: String| async move {
+        match cmd.as_str() {
+            "fail" => Err("upstream is gone"),
+            _ => Ok(format!("echo: {}", cmd)),
+        }
+    });
+    tokio::spawn(async move { server.serve_connection(server_io).await });
+
+    let mut conn = <Connection<DuplexStream>>::builder()
+        .handshake(client, "hunter2")
+        .await
+        .unwrap();
+    assert_eq!(conn.cmd("list").await.unwrap(), "echo: list");
+    assert!(conn.cmd("fail").await.is_err());
+}
+
+/// Hands out the scripted results of `accept`, then waits forever.
+struct ScriptedListener(Mutex<VecDeque<io::Result<DuplexStream>>>);
+
+impl Listener for ScriptedListener {
+    type Stream = DuplexStream;
+
+    fn accept(&self) -> Accept<'_, DuplexStream> {
+        let next = self.0.lock().unwrap().pop_front();
+        let address = SocketAddr::from(([127, 0, 0, 1], 25575));
+
+        Box::pin(async move {
+            match next {
+                Some(result) => result.map(|io| (io, address)),
+                None => std::future::pending().await,
+            }
+        })
+    }
+
+    fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+        tokio::spawn(future);
+    }
+
+    fn sleep(duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn accept_errors_do_not_stop_the_server() {
+    let (client, server_io) = tokio::io::duplex(4096);
+    let listener = ScriptedListener(Mutex::new(VecDeque::from(vec![
+        Err(io::ErrorKind::ConnectionAborted.into()),
+        // e.g. EMFILE
+        Err(io::Error::other("too many open files")),
+        Ok(server_io),
+    ])));
+    tokio::spawn(server().serve(listener));
+
+    let mut conn = <Connection<DuplexStream>>::builder()
+        .handshake(client, "hunter2")
+        .await
+        .unwrap();
+    assert_eq!(conn.cmd("list").await.unwrap(), "echo: list");
+}
+
+#[cfg(feature = "rt-tokio")]
+#[tokio::test]
+async fn serve_tokio_listener() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(server().serve(listener));
+
+    let mut conn = <Connection<tokio::net::TcpStream>>::builder()
+        .connect(address, "hunter2")
+        .await
+        .unwrap();
+    assert_eq!(conn.cmd("list").await.unwrap(), "echo: list");
+}
+
+#[cfg(feature = "rt-async-std")]
+#[async_std::test]
+async fn serve_async_std_listener() {
+    let listener = async_std::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap();
+    let address = listener.local_addr().unwrap();
+    async_std::task::spawn(server().serve(listener));
+
+    let mut conn = <Connection<rcon::AsyncStdStream>>::builder()
+        .connect(address, "hunter2")
+        .await
+        .unwrap();
+    assert_eq!(conn.cmd("list").await.unwrap(), "echo: list");
+}
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use rcon::{ClientSession, Event, PacketDecoder, PacketType, SourceMirror};
+
+mod common;
+
+use common::encode;
+
+#[test]
+fn decoder_handles_one_byte_at_a_time() {
+    let bytes = [
+        encode(1, PacketType::ResponseValue, b"first"),
+        encode(2, PacketType::ResponseValue, b""),
+    ]
+    .concat();
+    let mut decoder = PacketDecoder::new();
+    let mut packets = Vec::new();
+
+    for byte in bytes {
+        decoder.feed(&[byte]);
+        while let Some(packet) = decoder.decode().unwrap() {
+            packets.push(packet);
+        }
+    }
+
+    assert_eq!(packets.len(), 2);
+    assert_eq!(
+        (packets[0].get_id(), packets[0].get_body()),
+        (1, &b"first"[..])
+    );
+    assert_eq!((packets[1].get_id(), packets[1].get_body()), (2, &b""[..]));
+    assert_eq!(decoder.buffered(), 0);
+}
+
+#[test]
+fn decoder_handles_several_packets_in_one_feed() {
+    let mut bytes = [
+        encode(1, PacketType::ResponseValue, b"a"),
+        encode(2, PacketType::ResponseValue, b"b"),
+        encode(3, PacketType::ResponseValue, b"c"),
+    ]
+    .concat();
+    // the start of a fourth packet
+    bytes.extend_from_slice(&[10, 0, 0]);
+    let mut decoder = PacketDecoder::new();
+
+    decoder.feed(&bytes);
+
+    for (id, body) in [(1, b"a"), (2, b"b"), (3, b"c")] {
+        let packet = decoder.decode().unwrap().unwrap();
+        assert_eq!((packet.get_id(), packet.get_body()), (id, &body[..]));
+    }
+    assert!(decoder.decode().unwrap().is_none());
+    assert_eq!(decoder.buffered(), 3);
+}
+
+#[test]
+fn session_reports_failed_auth() {
+    let mut session = ClientSession::new();
+    let id = session.auth("hunter3");
+
+    let mut decoder = PacketDecoder::new();
+    decoder.feed(&session.poll_transmit().unwrap());
+    let auth = decoder.decode().unwrap().unwrap();
+    assert_eq!(auth.get_id(), id);
+    assert_eq!(auth.get_type(), PacketType::Auth);
+    assert_eq!(auth.get_body(), b"hunter3");
+    assert_eq!(session.poll_transmit(), None);
+
+    session
+        .receive(&encode(-1, PacketType::AuthResponse, b""))
+        .unwrap();
+
+    assert_eq!(session.poll_event(), Some(Event::AuthFailed));
+    assert_eq!(session.poll_event(), None);
+}
+
+#[test]
+fn session_handles_a_whole_exchange_in_one_feed() {
+    let mut session = ClientSession::new();
+    let auth_id = session.auth("hunter2");
+    let id = session.command("list");
+    let end_id = id + 1;
+
+    let bytes = [
+        // the preamble sent by Source dedicated servers
+        encode(auth_id, PacketType::ResponseValue, b""),
+        encode(auth_id, PacketType::AuthResponse, b""),
+        encode(id, PacketType::ResponseValue, b"There are "),
+        encode(id, PacketType::ResponseValue, b"0 players"),
+        encode(end_id, PacketType::ResponseValue, b""),
+    ]
+    .concat();
+    session.receive(&bytes).unwrap();
+
+    assert_eq!(session.poll_event(), Some(Event::Authenticated));
+    assert!(session.auth_preamble_received());
+    assert_eq!(
+        session.poll_event(),
+        Some(Event::Response {
+            id,
+            body: b"There are 0 players".to_vec()
+        })
+    );
+    assert_eq!(session.poll_event(), None);
+}
+
+#[test]
+fn source_mirror_waits_for_the_trailer() {
+    let mut session = ClientSession::new();
+    let id = session.command_with_terminator("status", Arc::new(SourceMirror));
+    let end_id = id + 1;
+
+    let bytes = [
+        encode(id, PacketType::ResponseValue, b"players : 0"),
+        // the mirrored packet
+        encode(end_id, PacketType::ResponseValue, b""),
+        encode(end_id, PacketType::ResponseValue, b"not the trailer"),
+    ]
+    .concat();
+    session.receive(&bytes).unwrap();
+    assert_eq!(session.poll_event(), None);
+
+    session
+        .receive(&encode(end_id, PacketType::ResponseValue, &[0, 0, 0, 1]))
+        .unwrap();
+    assert_eq!(
+        session.poll_event(),
+        Some(Event::Response {
+            id,
+            body: b"players : 0".to_vec()
+        })
+    );
+}
@@ -0,0 +1,57 @@
+use rcon::{Connection, Packet, PacketType, SourceMirror};
+use tokio::io::DuplexStream;
+
+mod common;
+
+const STATUS: &str = "hostname: Counter-Strike: Global Offensive\nversion : 1.38.2.2\nplayers : 2 humans, 0 bots (16/0 max)\n";
+
+/// A stand-in for a Source dedicated server.
+///
+/// It answers unknown and empty commands with "Unknown command" text, and mirrors
+/// empty `ResponseValue` packets followed by the `00 00 00 01 00 00 00 00` packet.
+fn srcds(request: Packet) -> Option<Vec<Packet>> {
+    let responses: Vec<(PacketType, Vec<u8>)> = match request.get_type() {
+        PacketType::Auth => vec![
+            (PacketType::ResponseValue, Vec::new()),
+            (PacketType::AuthResponse, Vec::new()),
+        ],
+        PacketType::ResponseValue => vec![
+            (PacketType::ResponseValue, Vec::new()),
+            (PacketType::ResponseValue, vec![0, 0, 0, 1, 0, 0, 0, 0]),
+        ],
+        _ if request.get_body() == b"status" => {
+            let (first, second) = STATUS.as_bytes().split_at(20);
+            vec![
+                (PacketType::ResponseValue, first.to_vec()),
+                (PacketType::ResponseValue, second.to_vec()),
+            ]
+        }
+        _ => vec![(
+            PacketType::ResponseValue,
+            format!(
+                "Unknown command \"{}\"\n",
+                String::from_utf8_lossy(request.get_body())
+            )
+            .into_bytes(),
+        )],
+    };
+
+    Some(
+        responses
+            .into_iter()
+            .map(|(ptype, body)| Packet::new(request.get_id(), ptype, body))
+            .collect(),
+    )
+}
+
+#[tokio::test]
+async fn source_mirror_detects_end_of_response() {
+    let mut conn = <Connection<DuplexStream>>::builder()
+        .response_terminator(SourceMirror)
+        .handshake(common::spawn(srcds), "hunter2")
+        .await
+        .unwrap();
+
+    assert_eq!(conn.cmd("status").await.unwrap(), STATUS);
+    assert_eq!(conn.cmd("status").await.unwrap(), STATUS);
+}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rcon::{
+    ClientSession, Connection, Event, IdleTimeout, KnownSuffix, Packet, PacketType, SinglePacket,
+};
+use tokio::io::DuplexStream;
+
+mod common;
+
+use common::encode;
+
+#[test]
+fn single_packet_completes_on_first_packet() {
+    let mut session = ClientSession::new().response_terminator(Arc::new(SinglePacket));
+    let id = session.command("seed");
+
+    // no end marker is sent
+    assert!(session.poll_transmit().is_some());
+    assert!(session.poll_transmit().is_none());
+
+    session
+        .receive(&encode(id, PacketType::ResponseValue, b"Seed: [42]"))
+        .unwrap();
+    assert_eq!(
+        session.poll_event(),
+        Some(Event::Response {
+            id,
+            body: b"Seed: [42]".to_vec()
+        })
+    );
+}
+
+#[test]
+fn known_suffix_split_across_packets() {
+    let mut session = ClientSession::new().response_terminator(Arc::new(KnownSuffix::new("\n> ")));
+    let id = session.command("status");
+
+    session
+        .receive(&encode(id, PacketType::ResponseValue, b"uptime: 3d\n"))
+        .unwrap();
+    assert_eq!(session.poll_event(), None);
+
+    session
+        .receive(&encode(id, PacketType::ResponseValue, b"> "))
+        .unwrap();
+    assert_eq!(
+        session.poll_event(),
+        Some(Event::Response {
+            id,
+            body: b"uptime: 3d\n> ".to_vec()
+        })
+    );
+}
+
+#[tokio::test]
+async fn idle_timeout_completes_after_silence() {
+    // the response has no end marker, and the connection stays open after it
+    let io = common::spawn(|request| {
+        Some(match request.get_type() {
+            PacketType::Auth => vec![common::echo(&request)],
+            _ => ["first ", "second"]
+                .iter()
+                .map(|part| {
+                    Packet::new(
+                        request.get_id(),
+                        PacketType::ResponseValue,
+                        part.as_bytes().to_vec(),
+                    )
+                })
+                .collect(),
+        })
+    });
+
+    let mut conn = <Connection<DuplexStream>>::builder()
+        .response_terminator(IdleTimeout(Duration::from_millis(50)))
+        .sleep_fn(tokio::time::sleep)
+        .handshake(io, "hunter2")
+        .await
+        .unwrap();
+
+    assert_eq!(conn.cmd("list").await.unwrap(), "first second");
+}
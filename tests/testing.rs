@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use rcon::testing::{Flavor, MockServer, Reply};
+use rcon::{Connection, Error, ServerProfile};
+use tokio::io::DuplexStream;
+
+#[tokio::test]
+async fn scripted_commands_are_answered() {
+    let (io, handle) = MockServer::new("hunter2")
+        .expect("list", Reply::text("There are 0 players"))
+        .respond("seed", Reply::text("Seed: [42]"))
+        .spawn();
+    let mut conn = <Connection<DuplexStream>>::builder()
+        .handshake(io, "hunter2")
+        .await
+        .unwrap();
+
+    assert_eq!(conn.cmd("seed").await.unwrap(), "Seed: [42]");
+    assert_eq!(conn.cmd("list").await.unwrap(), "There are 0 players");
+    assert_eq!(conn.cmd("seed").await.unwrap(), "Seed: [42]");
+
+    assert_eq!(handle.received(), ["seed", "list", "seed"]);
+    handle.assert_done();
+}
+
+#[tokio::test]
+#[should_panic(expected = "unexpected commands received")]
+async fn unexpected_commands_fail() {
+    let (io, handle) = MockServer::new("hunter2").spawn();
+    let mut conn = <Connection<DuplexStream>>::builder()
+        .handshake(io, "hunter2")
+        .await
+        .unwrap();
+
+    assert_eq!(conn.cmd("stop").await.unwrap(), "");
+    handle.assert_done();
+}
+
+#[tokio::test]
+#[should_panic(expected = "expected commands not received")]
+async fn missing_commands_fail() {
+    let (_io, handle) = MockServer::new("hunter2")
+        .expect("list", Reply::text(""))
+        .spawn();
+
+    handle.assert_done();
+}
+
+#[tokio::test]
+async fn wrong_password_is_rejected() {
+    let (io, _) = MockServer::new("hunter2").spawn();
+    let result = <Connection<DuplexStream>>::builder()
+        .handshake(io, "hunter3")
+        .await;
+
+    assert!(matches!(result, Err(Error::Auth)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn flavors_are_detected() {
+    for (flavor, profile) in [
+        (Flavor::Minecraft, ServerProfile::Minecraft),
+        (Flavor::Factorio, ServerProfile::Factorio),
+        (Flavor::Source, ServerProfile::Source),
+        (Flavor::Generic, ServerProfile::Generic),
+    ] {
+        let (io, handle) = MockServer::new("hunter2")
+            .flavor(flavor)
+            .expect("help", Reply::text("x".repeat(10_000)))
+            .spawn();
+        let mut conn = <Connection<DuplexStream>>::builder()
+            .auto_detect()
+            .sleep_fn(tokio::time::sleep)
+            .handshake(io, "hunter2")
+            .await
+            .unwrap();
+
+        assert_eq!(format!("{:?}", conn.profile()), format!("{:?}", profile));
+        assert_eq!(conn.cmd("help").await.unwrap(), "x".repeat(10_000));
+        handle.assert_done();
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn delayed_and_dropped_replies_time_out() {
+    let (io, handle) = MockServer::new("hunter2")
+        .expect("slow", Reply::text("done").delayed(Duration::from_secs(10)))
+        .expect("frozen", Reply::drop())
+        .expect("list", Reply::text("There are 0 players"))
+        .spawn();
+    let mut conn = <Connection<DuplexStream>>::builder()
+        .sleep_fn(tokio::time::sleep)
+        .handshake(io, "hunter2")
+        .await
+        .unwrap();
+
+    let result = conn.cmd_with_timeout("slow", Duration::from_secs(5)).await;
+    assert!(matches!(result, Err(Error::Timeout)));
+    let result = conn
+        .cmd_with_timeout("frozen", Duration::from_secs(5))
+        .await;
+    assert!(matches!(result, Err(Error::Timeout)));
+
+    // the late reply to "slow" is discarded
+    assert_eq!(conn.cmd("list").await.unwrap(), "There are 0 players");
+    handle.assert_done();
+}
+
+#[tokio::test]
+async fn disconnect_closes_the_connection() {
+    let (io, _) = MockServer::new("hunter2")
+        .expect("stop", Reply::disconnect())
+        .spawn();
+    let mut conn = <Connection<DuplexStream>>::builder()
+        .handshake(io, "hunter2")
+        .await
+        .unwrap();
+
+    assert!(matches!(conn.cmd("stop").await, Err(Error::Io(_))));
+}
+
+#[cfg(feature = "rt-tokio")]
+#[tokio::test]
+async fn listen_on_tcp() {
+    let (address, handle) = MockServer::new("hunter2")
+        .respond("list", Reply::text("There are 0 players"))
+        .listen()
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        let mut conn = <Connection<tokio::net::TcpStream>>::connect(address, "hunter2")
+            .await
+            .unwrap();
+        assert_eq!(conn.cmd("list").await.unwrap(), "There are 0 players");
+    }
+
+    assert_eq!(handle.received(), ["list", "list"]);
+}
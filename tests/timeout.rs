@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use rcon::{Backoff, Connection, Error, PacketType, SinglePacket};
+use tokio::io::DuplexStream;
+
+mod common;
+
+/// A stand-in server that never answers the `freeze` command and, if `auth` is
+/// false, the authentication request.
+fn frozen(auth: bool) -> DuplexStream {
+    common::spawn(move |request| {
+        Some(match (request.get_type(), request.get_body()) {
+            (PacketType::Auth, _) if auth => vec![common::echo(&request)],
+            (PacketType::Auth, _) | (_, b"freeze") => Vec::new(),
+            _ => vec![common::echo(&request)],
+        })
+    })
+}
+
+fn builder(auth: bool) -> (rcon::Builder<DuplexStream>, DuplexStream) {
+    let builder = <Connection<DuplexStream>>::builder()
+        .response_terminator(SinglePacket)
+        .sleep_fn(tokio::time::sleep);
+
+    (builder, frozen(auth))
+}
+
+#[tokio::test(start_paused = true)]
+async fn auth_timeout() {
+    let (builder, io) = builder(false);
+
+    let result = builder
+        .auth_timeout(Duration::from_secs(5))
+        .handshake(io, "hunter2")
+        .await;
+    assert!(matches!(result, Err(Error::Timeout)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn command_timeout_keeps_connection_usable() {
+    let (builder, io) = builder(true);
+    let mut conn = builder
+        .command_timeout(Duration::from_secs(5))
+        .handshake(io, "hunter2")
+        .await
+        .unwrap();
+
+    assert!(matches!(conn.cmd("freeze").await, Err(Error::Timeout)));
+    assert_eq!(conn.cmd("list").await.unwrap(), "list");
+}
+
+#[tokio::test(start_paused = true)]
+async fn cmd_with_timeout() {
+    let (builder, io) = builder(true);
+    let mut conn = builder.handshake(io, "hunter2").await.unwrap();
+
+    let result = conn
+        .cmd_with_timeout("freeze", Duration::from_secs(1))
+        .await;
+    assert!(matches!(result, Err(Error::Timeout)));
+    assert_eq!(
+        conn.cmd_with_timeout("list", Duration::from_secs(1))
+            .await
+            .unwrap(),
+        "list"
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn client_command_timeout() {
+    let (builder, io) = builder(true);
+    let conn = builder.handshake(io, "hunter2").await.unwrap();
+    let (client, driver) = conn.into_client();
+    let driver = tokio::spawn(driver.run());
+
+    let result = client
+        .cmd_with_timeout("freeze", Duration::from_secs(1))
+        .await;
+    assert!(matches!(result, Err(Error::Timeout)));
+    assert_eq!(client.cmd("list").await.unwrap(), "list");
+
+    // the driver does not wait for the abandoned command
+    drop(client);
+    driver.await.unwrap().unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn connect_timeout() {
+    let mut conn = <Connection<DuplexStream>>::builder()
+        .connect_timeout(Duration::from_secs(5))
+        .sleep_fn(tokio::time::sleep)
+        // a server that never accepts the connection
+        .reconnect_with(std::future::pending, "hunter2")
+        .backoff(Backoff {
+            max_attempts: Some(1),
+            ..Backoff::default()
+        });
+
+    let start = tokio::time::Instant::now();
+    assert!(matches!(conn.connect().await, Err(Error::Timeout)));
+    assert_eq!(start.elapsed(), Duration::from_secs(5));
+}